@@ -0,0 +1,234 @@
+use alloc::vec::Vec;
+use bios::{compress_huffman_with_options, compress_lz77_with_options, compress_rle_optimal_with_options};
+use bios::{filter_diff8_with_options, filter_diff16_with_options, HuffmanBitLength, Lz77Level, Lz77Options};
+use bios::{BiosCompressionType, BiosHeader, bios_compression_type, consumed_length, decompress_with_end, decompress_with_options};
+use codec::Codec;
+use error::{Error, Result};
+use options::{check_output_size, CompressOptions, DecompressOptions};
+
+// Guards against streams that keep decoding into further valid headers
+const MAX_CHAIN_LENGTH: usize = 8;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum BiosMethod {
+    Lz77,
+    Huffman4,
+    Huffman8,
+    Rle,
+    DiffFilter8,
+    DiffFilter16,
+}
+
+fn compress_method(input: &[u8], method: BiosMethod, vram_safe: bool, options: &CompressOptions) -> Result<Vec<u8>> {
+    match method {
+        BiosMethod::Lz77 => compress_lz77_with_options(input, &Lz77Options {
+            level: Lz77Level::Optimal,
+            vram_safe,
+            compress_options: *options,
+            ..Lz77Options::default()
+        }),
+        BiosMethod::Huffman4 => compress_huffman_with_options(input, HuffmanBitLength::Bits4, options),
+        BiosMethod::Huffman8 => compress_huffman_with_options(input, HuffmanBitLength::Bits8, options),
+        BiosMethod::Rle => compress_rle_optimal_with_options(input, options),
+        BiosMethod::DiffFilter8 => filter_diff8_with_options(input, options),
+        BiosMethod::DiffFilter16 => filter_diff16_with_options(input, options),
+    }
+}
+
+fn method_from_header(header: u8) -> Option<BiosMethod> {
+    match (bios_compression_type(header), header & 0xF) {
+        (Some(BiosCompressionType::Lz77), _) => Some(BiosMethod::Lz77),
+        (Some(BiosCompressionType::Huffman), 4) => Some(BiosMethod::Huffman4),
+        (Some(BiosCompressionType::Huffman), 8) => Some(BiosMethod::Huffman8),
+        (Some(BiosCompressionType::Rle), _) => Some(BiosMethod::Rle),
+        (Some(BiosCompressionType::DiffFilter), 1) => Some(BiosMethod::DiffFilter8),
+        (Some(BiosCompressionType::DiffFilter), 2) => Some(BiosMethod::DiffFilter16),
+        _ => None,
+    }
+}
+
+impl Codec for BiosMethod {
+    fn name(&self) -> &str {
+        match *self {
+            BiosMethod::Lz77 => "lz77",
+            BiosMethod::Huffman4 => "huffman4",
+            BiosMethod::Huffman8 => "huffman8",
+            BiosMethod::Rle => "rle",
+            BiosMethod::DiffFilter8 => "diff8",
+            BiosMethod::DiffFilter16 => "diff16",
+        }
+    }
+
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        compress_method(input, *self, false, &CompressOptions::default())
+    }
+
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        BiosHeader::parse(input)?;
+
+        if method_from_header(input[0]) != Some(*self) {
+            return Err(Error::HeaderMismatch { input_offset: 0 });
+        }
+
+        decompress_with_options(input, &DecompressOptions::default()).map(|(output, _, _)| output)
+    }
+
+    fn detect(&self, input: &[u8]) -> bool {
+        BiosHeader::parse(input).is_ok() && (method_from_header(input[0]) == Some(*self))
+    }
+}
+
+pub fn compress_chain_with_options(input: &[u8], methods: &[BiosMethod], vram_safe: bool, options: &CompressOptions) -> Result<Vec<u8>> {
+    if methods.len() > MAX_CHAIN_LENGTH {
+        return Err(Error::InvalidArgument("chain too long"));
+    }
+
+    // Only the outer layer is padded, since inner layers are never loaded
+    // from an aligned address directly.
+    let inner_options = CompressOptions { alignment_padding: false, ..*options };
+
+    let mut output = input.to_vec();
+    for (i, &method) in methods.iter().enumerate() {
+        let options = if i + 1 == methods.len() { options } else { &inner_options };
+        output = compress_method(&output, method, vram_safe, options)?;
+    }
+
+    Ok(output)
+}
+
+pub fn compress_chain(input: &[u8], methods: &[BiosMethod], vram_safe: bool) -> Result<Vec<u8>> {
+    compress_chain_with_options(input, methods, vram_safe, &CompressOptions::default())
+}
+
+// An inner layer has to fill its buffer entirely, save for zero padding up
+// to the next 32-bit boundary.
+fn check_whole_stream(input: &[u8], end: usize) -> Result<()> {
+    let length = consumed_length(end, input);
+    if length != input.len() {
+        return Err(Error::SizeMismatch { expected: length, actual: input.len() });
+    }
+
+    match input[end..].iter().position(|&byte| byte != 0) {
+        Some(i) => Err(Error::InvalidPadding { input_offset: end + i }),
+        None => Ok(()),
+    }
+}
+
+// The methods are given in the order compress_chain applied them, so that
+// no layer has to be guessed.
+pub fn decompress_chain_exact_with_options(input: &[u8], methods: &[BiosMethod], options: &DecompressOptions) -> Result<Vec<u8>> {
+    if methods.len() > MAX_CHAIN_LENGTH {
+        return Err(Error::InvalidArgument("chain too long"));
+    }
+
+    let layer_options = DecompressOptions { expected_size: None, ..*options };
+    let inner_options = DecompressOptions { alignment_padding: false, ..layer_options };
+
+    let mut output = input.to_vec();
+    for (i, &method) in methods.iter().rev().enumerate() {
+        let options = if i == 0 { &layer_options } else { &inner_options };
+
+        BiosHeader::parse_with_options(&output, options)?;
+        if method_from_header(output[0]) != Some(method) {
+            return Err(Error::HeaderMismatch { input_offset: 0 });
+        }
+
+        let (inner_output, end, _) = decompress_with_end(&output, options)?;
+        if i > 0 {
+            check_whole_stream(&output, end)?;
+        }
+
+        output = inner_output;
+    }
+
+    check_output_size(options, output.len())?;
+    Ok(output)
+}
+
+pub fn decompress_chain_exact(input: &[u8], methods: &[BiosMethod]) -> Result<Vec<u8>> {
+    decompress_chain_exact_with_options(input, methods, &DecompressOptions::default())
+}
+
+// Plain data can look like a valid stream by chance, so when the methods are
+// known decompress_chain_exact should be preferred.
+pub fn decompress_chain_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, Vec<BiosMethod>)> {
+    // Every layer is held to the size limit, while the expected size only
+    // applies to the plain data at the end of the chain. Inner layers never
+    // had to be aligned, so only the outer one is checked for padding.
+    let layer_options = DecompressOptions { expected_size: None, ..*options };
+    let inner_options = DecompressOptions { alignment_padding: false, ..layer_options };
+
+    let (mut output, _, _) = decompress_with_options(input, &layer_options)?;
+    let mut methods = vec![method_from_header(input[0]).ok_or(Error::HeaderMismatch { input_offset: 0 })?];
+
+    // A decoded layer is unwrapped further only when it is entirely made up
+    // of another valid stream, otherwise it is considered plain data. Empty
+    // layers are kept as well, a bare header is more likely to be data.
+    while methods.len() < MAX_CHAIN_LENGTH {
+        match decompress_with_end(&output, &inner_options) {
+            Ok((inner_output, end, _)) if !inner_output.is_empty() && check_whole_stream(&output, end).is_ok() => {
+                match method_from_header(output[0]) {
+                    Some(method) => methods.push(method),
+                    None => break,
+                }
+                output = inner_output;
+            },
+            _ => break,
+        }
+    }
+
+    check_output_size(options, output.len())?;
+
+    methods.reverse();
+    Ok((output, methods))
+}
+
+pub fn decompress_chain(input: &[u8]) -> Result<(Vec<u8>, Vec<BiosMethod>)> {
+    decompress_chain_with_options(input, &DecompressOptions::default())
+}
+
+pub fn compress_best_with_options(input: &[u8], vram_safe: bool, try_filters: bool, options: &CompressOptions) -> Result<(Vec<u8>, Vec<BiosMethod>)> {
+    let compression_methods = [BiosMethod::Lz77, BiosMethod::Huffman4, BiosMethod::Huffman8, BiosMethod::Rle];
+
+    let mut filter_methods = vec![None];
+    if try_filters {
+        filter_methods.push(Some(BiosMethod::DiffFilter8));
+        if input.len() % 2 == 0 {
+            filter_methods.push(Some(BiosMethod::DiffFilter16));
+        }
+    }
+
+    // A candidate that fails is skipped, the error is only returned when no
+    // candidate succeeded at all.
+    let mut best: Result<(Vec<u8>, Vec<BiosMethod>)> = Err(Error::InvalidArgument("no compression method available"));
+
+    for &filter_method in &filter_methods {
+        for &compression_method in &compression_methods {
+            let methods: Vec<BiosMethod> = filter_method.into_iter().chain(Some(compression_method)).collect();
+
+            match compress_chain_with_options(input, &methods, vram_safe, options) {
+                Ok(output) => {
+                    let is_better = match best {
+                        Ok((ref best_output, _)) => output.len() < best_output.len(),
+                        Err(_) => true,
+                    };
+
+                    if is_better {
+                        best = Ok((output, methods));
+                    }
+                },
+                Err(error) => {
+                    if best.is_err() {
+                        best = Err(error);
+                    }
+                },
+            }
+        }
+    }
+
+    best
+}
+
+pub fn compress_best(input: &[u8], vram_safe: bool, try_filters: bool) -> Result<(Vec<u8>, Vec<BiosMethod>)> {
+    compress_best_with_options(input, vram_safe, try_filters, &CompressOptions::default())
+}
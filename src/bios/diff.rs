@@ -1,105 +1,202 @@
-use std::io::{Read, Cursor, Result, Error, ErrorKind};
-use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
-use bios::{BiosCompressionType, bios_compression_type};
-use num::FromPrimitive;
-
-enum_from_primitive! {
-    #[derive(Debug, Eq, PartialEq)]
-    enum FilterType {
-        Diff8  = 1,
-        Diff16 = 2,
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use byteorder::{ByteOrder, LittleEndian};
+use bios::{BiosCompressionType, BiosHeader, consumed_length, allocate_output, check_padding, pad_output};
+use error::{Error, Result};
+use options::{check_output_size, CompressOptions, DecompressOptions};
+use utils::{ByteReader, WriteExt};
+
+#[derive(Debug, Eq, PartialEq)]
+enum FilterType {
+    Diff8  = 1,
+    Diff16 = 2,
+}
+
+impl TryFrom<u8> for FilterType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<FilterType> {
+        match value {
+            1 => Ok(FilterType::Diff8),
+            2 => Ok(FilterType::Diff16),
+            _ => Err(Error::HeaderMismatch { input_offset: 0 }),
+        }
     }
 }
 
-pub fn filter_diff8(input: &[u8]) -> Result<Vec<u8>> {
-    let mut output = Vec::with_capacity(input.len() + 4);
+pub fn filter_diff8_with_options(input: &[u8], options: &CompressOptions) -> Result<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::with_capacity(input.len() + 4);
 
-    output.write_u8(((BiosCompressionType::DiffFilter as u8) << 4) | (FilterType::Diff8 as u8))?;
-    output.write_u24::<LittleEndian>(input.len() as u32)?;
+    let header = BiosHeader { kind: BiosCompressionType::DiffFilter, parameter: FilterType::Diff8 as u8, size: input.len() };
+    header.write_into(&mut output, options)?;
 
     if !input.is_empty() {
-        output.write_u8(input[0])?;
+        output.write_u8(input[0]);
         for i in 1..input.len() {
-            output.write_u8(input[i].wrapping_sub(input[i - 1]))?;
+            output.write_u8(input[i].wrapping_sub(input[i - 1]));
         }
     }
 
+    pad_output(&mut output, options);
     Ok(output)
 }
 
-pub fn unfilter_diff8(input: &[u8]) -> Result<Vec<u8>> {
-    let mut cursor = Cursor::new(input);
-    let header = cursor.read_u8()?;
+pub fn filter_diff8(input: &[u8]) -> Result<Vec<u8>> {
+    filter_diff8_with_options(input, &CompressOptions::default())
+}
+
+fn unfilter_diff8_into_with_end(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<(usize, usize)> {
+    let mut reader = ByteReader::new(input);
+    let header = BiosHeader::read(&mut reader, options)?;
 
-    if (bios_compression_type(header) != Some(BiosCompressionType::DiffFilter)) ||
-        (FilterType::from_u8(header & 0xF) != Some(FilterType::Diff8)) {
-        return Err(Error::new(ErrorKind::InvalidData, "filter header mismatch"));
+    if (header.kind != BiosCompressionType::DiffFilter) || (header.parameter != FilterType::Diff8 as u8) {
+        return Err(Error::HeaderMismatch { input_offset: 0 });
     }
 
-    let data_size: usize = cursor.read_u24::<LittleEndian>()? as usize;
+    check_output_size(options, header.size)?;
+
+    let data_size = header.size;
+    if data_size > output.len() {
+        return Err(Error::SizeLimit { size: data_size, limit: output.len() });
+    }
 
-    let mut output: Vec<u8> = vec![0; data_size];
-    cursor.read_exact(&mut output)?;
+    let output = &mut output[..data_size];
+    output.copy_from_slice(reader.read_bytes(data_size, 0)?);
 
     for i in 1..output.len() {
         let data = output[i - 1].wrapping_add(output[i]);
         output[i] = data;
     }
 
-    assert_eq!(output.len(), data_size);
-    Ok(output)
+    check_padding(input, reader.position(), data_size, options)?;
+    Ok((data_size, reader.position()))
 }
 
-pub fn filter_diff16(input: &[u8]) -> Result<Vec<u8>> {
+pub fn unfilter_diff8_into_with_options(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<usize> {
+    unfilter_diff8_into_with_end(input, output, options).map(|(written, _)| written)
+}
+
+pub fn unfilter_diff8_into(input: &[u8], output: &mut [u8]) -> Result<usize> {
+    unfilter_diff8_into_with_options(input, output, &DecompressOptions::default())
+}
+
+pub fn unfilter_diff8_with_end(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut output = allocate_output(input, options)?;
+    let (_, end) = unfilter_diff8_into_with_end(input, &mut output, options)?;
+    Ok((output, end))
+}
+
+pub fn unfilter_diff8_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    unfilter_diff8_with_end(input, options).map(|(output, end)| (output, consumed_length(end, input)))
+}
+
+pub fn unfilter_diff8_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    unfilter_diff8_with_options(input, &DecompressOptions::default())
+}
+
+pub fn unfilter_diff8(input: &[u8]) -> Result<Vec<u8>> {
+    unfilter_diff8_with_length(input).map(|(output, _)| output)
+}
+
+pub fn filter_diff16_with_options(input: &[u8], options: &CompressOptions) -> Result<Vec<u8>> {
     if input.len() % 2 == 0 {
-        let mut output = Vec::with_capacity(input.len() + 4);
+        let mut output: Vec<u8> = Vec::with_capacity(input.len() + 4);
 
-        output.write_u8(((BiosCompressionType::DiffFilter as u8) << 4) | (FilterType::Diff16 as u8))?;
-        output.write_u24::<LittleEndian>(input.len() as u32)?;
+        let header = BiosHeader { kind: BiosCompressionType::DiffFilter, parameter: FilterType::Diff16 as u8, size: input.len() };
+        header.write_into(&mut output, options)?;
 
         let mut input16: Vec<u16> = vec![0; input.len() / 2];
         LittleEndian::read_u16_into(input, &mut input16[..]);
 
         if !input16.is_empty() {
-            output.write_u16::<LittleEndian>(input16[0])?;
+            output.write_u16_le(input16[0]);
             for i in 1..input16.len() {
-                output.write_u16::<LittleEndian>(input16[i].wrapping_sub(input16[i - 1]))?;
+                output.write_u16_le(input16[i].wrapping_sub(input16[i - 1]));
             }
         }
 
+        pad_output(&mut output, options);
         Ok(output)
     } else {
-        Err(Error::new(ErrorKind::InvalidData, "data size must be some multiple of 2"))
+        Err(Error::InvalidArgument("data size must be some multiple of 2"))
     }
 }
 
-pub fn unfilter_diff16(input: &[u8]) -> Result<Vec<u8>> {
-    let mut cursor = Cursor::new(input);
-    let header = cursor.read_u8()?;
+pub fn filter_diff16(input: &[u8]) -> Result<Vec<u8>> {
+    filter_diff16_with_options(input, &CompressOptions::default())
+}
 
-    if (bios_compression_type(header) != Some(BiosCompressionType::DiffFilter)) ||
-        (FilterType::from_u8(header & 0xF) != Some(FilterType::Diff16)) {
-        return Err(Error::new(ErrorKind::InvalidData, "filter header mismatch"));
+fn unfilter_diff16_into_with_end(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<(usize, usize)> {
+    let mut reader = ByteReader::new(input);
+    let header = BiosHeader::read(&mut reader, options)?;
+
+    if (header.kind != BiosCompressionType::DiffFilter) || (header.parameter != FilterType::Diff16 as u8) {
+        return Err(Error::HeaderMismatch { input_offset: 0 });
     }
 
-    let data_size: usize = cursor.read_u24::<LittleEndian>()? as usize;
+    check_output_size(options, header.size)?;
+
+    let data_size = header.size;
     if data_size % 2 != 0 {
-        return Err(Error::new(ErrorKind::InvalidData, "data size must be some multiple of 2"));
+        return Err(Error::InvalidSize { size: data_size });
     }
 
-    let mut buffer: Vec<u16> = vec![0; data_size / 2];
-    cursor.read_u16_into::<LittleEndian>(&mut buffer)?;
+    if data_size > output.len() {
+        return Err(Error::SizeLimit { size: data_size, limit: output.len() });
+    }
+
+    let output = &mut output[..data_size];
+    output.copy_from_slice(reader.read_bytes(data_size, 0)?);
 
-    for i in 1..buffer.len() {
-        let data = buffer[i - 1].wrapping_add(buffer[i]);
-        buffer[i] = data;
+    for i in 1..(data_size / 2) {
+        let data = LittleEndian::read_u16(&output[i * 2 - 2..]).wrapping_add(LittleEndian::read_u16(&output[i * 2..]));
+        LittleEndian::write_u16(&mut output[i * 2..], data);
     }
 
-    let mut output: Vec<u8> = vec![0; data_size];
-    LittleEndian::write_u16_into(&buffer, &mut output[..]);
+    check_padding(input, reader.position(), data_size, options)?;
+    Ok((data_size, reader.position()))
+}
 
-    assert_eq!(buffer.len(), data_size / 2);
-    assert_eq!(output.len(), data_size);
+pub fn unfilter_diff16_into_with_options(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<usize> {
+    unfilter_diff16_into_with_end(input, output, options).map(|(written, _)| written)
+}
 
-    Ok(output)
+pub fn unfilter_diff16_into(input: &[u8], output: &mut [u8]) -> Result<usize> {
+    unfilter_diff16_into_with_options(input, output, &DecompressOptions::default())
+}
+
+pub fn unfilter_diff16_with_end(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut output = allocate_output(input, options)?;
+    let (_, end) = unfilter_diff16_into_with_end(input, &mut output, options)?;
+    Ok((output, end))
+}
+
+pub fn unfilter_diff16_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    unfilter_diff16_with_end(input, options).map(|(output, end)| (output, consumed_length(end, input)))
+}
+
+pub fn unfilter_diff16_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    unfilter_diff16_with_options(input, &DecompressOptions::default())
+}
+
+pub fn unfilter_diff16(input: &[u8]) -> Result<Vec<u8>> {
+    unfilter_diff16_with_length(input).map(|(output, _)| output)
+}
+
+pub fn unfilter_diff_with_end(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut reader = ByteReader::new(input);
+    let header = reader.read_u8(0)?;
+
+    match FilterType::try_from(header & 0xF)? {
+        FilterType::Diff8 => unfilter_diff8_with_end(input, options),
+        FilterType::Diff16 => unfilter_diff16_with_end(input, options),
+    }
+}
+
+pub fn unfilter_diff_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    unfilter_diff_with_end(input, options).map(|(output, end)| (output, consumed_length(end, input)))
+}
+
+pub fn unfilter_diff_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    unfilter_diff_with_options(input, &DecompressOptions::default())
 }
@@ -1,6 +1,16 @@
-use std::io::{Cursor, Read, Result, Error, ErrorKind};
-use byteorder::{LittleEndian, ReadBytesExt};
-use bios::{BiosCompressionType, bios_compression_type};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use bios::{BiosCompressionType, BiosHeader, consumed_length, allocate_output, check_padding, pad_output};
+use error::{Error, Result};
+use options::{check_output_size, CompressOptions, DecompressOptions};
+use utils::{ByteReader, WriteExt};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum HuffmanBitLength {
+    Auto,
+    Bits4,
+    Bits8,
+}
 
 #[derive(Debug)]
 enum HuffmanNode {
@@ -13,76 +23,332 @@ enum HuffmanNode {
     },
 }
 
-pub fn decompress_huffman(input: &[u8]) -> Result<Vec<u8>> {
-    let mut cursor = Cursor::new(input);
-    let header = cursor.read_u8()?;
+fn decompress_huffman_into_with_end(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<(usize, usize)> {
+    let mut reader = ByteReader::new(input);
+    let header = BiosHeader::read(&mut reader, options)?;
+
+    if header.kind != BiosCompressionType::Huffman {
+        return Err(Error::HeaderMismatch { input_offset: 0 });
+    }
+
+    check_output_size(options, header.size)?;
 
-    if bios_compression_type(header) != Some(BiosCompressionType::Huffman) {
-        return Err(Error::new(ErrorKind::InvalidData, "compression header mismatch"));
+    let bit_length = header.parameter;
+    let decompressed_size = header.size;
+    if decompressed_size > output.len() {
+        return Err(Error::SizeLimit { size: decompressed_size, limit: output.len() });
     }
 
-    let bit_length = header & 0xF;
-    let decompressed_size: usize = cursor.read_u24::<LittleEndian>()? as usize;
+    // The tree is walked in place instead of being parsed up front
+    let tree_size = reader.read_u8(0)? as usize * 2 + 1;
+    let tree_offset = reader.position();
+    let tree_data = reader.read_bytes(tree_size, 0)?;
 
-    let huffman_tree = {
-        let tree_size = cursor.read_u8()? as usize * 2 + 1;
+    let mut position = 0;
+    let mut bits = 0;
+    let mut remaining_bits = 0;
+    let mut current_offset = 0;
+    let mut low_nibble: Option<u8> = None;
 
-        let mut tree_data: Vec<u8> = vec![0; tree_size];
-        cursor.read_exact(&mut tree_data)?;
+    while position < decompressed_size {
+        if remaining_bits == 0 {
+            bits = reader.read_u32_le(position)?;
+            remaining_bits = 32;
+        }
+
+        let node = tree_data[current_offset];
+        let bit = (bits >> 31) as usize;
+        let is_leaf = ((node << bit) & 0x80) != 0;
+        let child_offset = (((current_offset + 3) & !1) - 1) + (node & 0x3F) as usize * 2 + bit;
 
-        fn read_node(tree_data: &[u8], offset: usize, is_leaf: bool) -> Result<HuffmanNode> {
-            let node = *tree_data.get(offset).ok_or_else(|| Error::new(ErrorKind::InvalidData, "node offset out of bounds"))?;
+        let child = *tree_data.get(child_offset)
+            .ok_or(Error::InvalidTree { input_offset: tree_offset + current_offset, output_position: position })?;
 
-            if is_leaf {
-                Ok(HuffmanNode::Leaf {
-                    value: node,
-                })
+        if is_leaf {
+            if bit_length == 8 {
+                output[position] = child;
+                position += 1;
+            } else if let Some(low_nibble) = low_nibble.take() {
+                // 4-bit symbols are stored low nibble first
+                output[position] = ((child & 0xF) << 4) | low_nibble;
+                position += 1;
             } else {
-                let node0_leaf = ((node >> 7) & 1) == 1;
-                let node1_leaf = ((node >> 6) & 1) == 1;
-                let node0_offset = (((offset + 3) & !1) - 1) as usize + (node & 0x3F) as usize * 2;
-                let node1_offset = node0_offset + 1;
-
-                Ok(HuffmanNode::Branch {
-                    node0: Box::new(read_node(tree_data, node0_offset, node0_leaf)?),
-                    node1: Box::new(read_node(tree_data, node1_offset, node1_leaf)?),
-                })
+                low_nibble = Some(child & 0xF);
             }
+            current_offset = 0;
+        } else {
+            current_offset = child_offset;
         }
 
-        read_node(&tree_data, 0, false)?
-    };
+        bits <<= 1;
+        remaining_bits -= 1;
+    }
 
-    let mut output: Vec<u8> = Vec::with_capacity(decompressed_size);
-    let mut bits = cursor.read_u32::<LittleEndian>()?;
-    let mut remaining_bits = 32;
-    let mut current_node = &huffman_tree;
+    check_padding(input, reader.position(), position, options)?;
+    Ok((position, reader.position()))
+}
 
-    while output.len() < decompressed_size {
-        if let &HuffmanNode::Branch { ref node0, ref node1 } = current_node {
-            current_node = if bits & 0x80000000 == 0 { node0 } else { node1 };
+pub fn decompress_huffman_into_with_options(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<usize> {
+    decompress_huffman_into_with_end(input, output, options).map(|(written, _)| written)
+}
 
-            if let &HuffmanNode::Leaf { value } = current_node {
-                output.push(value);
-                current_node = &huffman_tree;
-            }
+pub fn decompress_huffman_into(input: &[u8], output: &mut [u8]) -> Result<usize> {
+    decompress_huffman_into_with_options(input, output, &DecompressOptions::default())
+}
+
+pub fn decompress_huffman_with_end(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut output = allocate_output(input, options)?;
+    let (_, end) = decompress_huffman_into_with_end(input, &mut output, options)?;
+    Ok((output, end))
+}
+
+pub fn decompress_huffman_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    decompress_huffman_with_end(input, options).map(|(output, end)| (output, consumed_length(end, input)))
+}
+
+pub fn decompress_huffman_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    decompress_huffman_with_options(input, &DecompressOptions::default())
+}
+
+pub fn decompress_huffman(input: &[u8]) -> Result<Vec<u8>> {
+    decompress_huffman_with_length(input).map(|(output, _)| output)
+}
+
+fn build_tree(frequencies: &[usize]) -> HuffmanNode {
+    let mut nodes: Vec<(usize, HuffmanNode)> = frequencies.iter()
+        .enumerate()
+        .filter(|&(_, &frequency)| frequency > 0)
+        .map(|(value, &frequency)| (frequency, HuffmanNode::Leaf { value: value as u8 }))
+        .collect();
+
+    // The root node is always a branch, so at least two leaves are needed
+    // even when the input has fewer distinct symbols.
+    let mut padding_value = 0;
+    while nodes.len() < 2 {
+        if frequencies[padding_value] == 0 {
+            nodes.push((0, HuffmanNode::Leaf { value: padding_value as u8 }));
+        }
+        padding_value += 1;
+    }
+
+    fn take_smallest(nodes: &mut Vec<(usize, HuffmanNode)>) -> (usize, HuffmanNode) {
+        let (index, _) = nodes.iter()
+            .enumerate()
+            .min_by_key(|&(index, &(weight, _))| (weight, index))
+            .unwrap();
+        nodes.remove(index)
+    }
+
+    while nodes.len() > 1 {
+        let (weight0, node0) = take_smallest(&mut nodes);
+        let (weight1, node1) = take_smallest(&mut nodes);
+
+        nodes.push((weight0 + weight1, HuffmanNode::Branch {
+            node0: Box::new(node0),
+            node1: Box::new(node1),
+        }));
+    }
+
+    nodes.pop().unwrap().1
+}
+
+fn collect_codes(node: &HuffmanNode, code: &mut Vec<bool>, codes: &mut Vec<Vec<bool>>) {
+    match *node {
+        HuffmanNode::Branch { ref node0, ref node1 } => {
+            code.push(false);
+            collect_codes(node0, code, codes);
+            code.pop();
+
+            code.push(true);
+            collect_codes(node1, code, codes);
+            code.pop();
+        },
+        HuffmanNode::Leaf { value } => {
+            codes[value as usize] = code.clone();
+        },
+    }
+}
+
+fn leaf_count(node: &HuffmanNode) -> usize {
+    match *node {
+        HuffmanNode::Branch { ref node0, ref node1 } => leaf_count(node0) + leaf_count(node1),
+        HuffmanNode::Leaf { .. } => 1,
+    }
+}
+
+fn write_tree(huffman_tree: &HuffmanNode) -> Result<Vec<u8>> {
+    // Every branch points to its child pair with a 6-bit offset, so the pair
+    // has to be placed within the next 64 pairs after the branch itself.
+    // Branches are scheduled smallest subtree first, which keeps the number of
+    // unplaced branches low, falling back to the earliest deadline whenever
+    // delaying it would make some of the waiting branches unplaceable.
+    fn deadline(offset: usize) -> usize {
+        offset.div_ceil(2) + 0x3F
+    }
+
+    fn is_feasible(mut deadlines: Vec<usize>, pair_index: usize) -> bool {
+        deadlines.sort();
+        deadlines.iter().enumerate().all(|(i, &deadline)| deadline >= pair_index + i)
+    }
 
-            bits <<= 1;
-            remaining_bits -= 1;
+    let mut tree_data: Vec<u8> = vec![0];
+    let mut pending: Vec<(&HuffmanNode, usize, usize)> = vec![(huffman_tree, 0, leaf_count(huffman_tree))];
 
-            if (remaining_bits == 0) && (output.len() < decompressed_size) {
-                bits = cursor.read_u32::<LittleEndian>()?;
-                remaining_bits = 32;
+    while !pending.is_empty() {
+        let pair_index = (tree_data.len() - 1) / 2;
+
+        let smallest = (0..pending.len())
+            .min_by_key(|&i| (pending[i].2, deadline(pending[i].1)))
+            .unwrap();
+        let earliest = (0..pending.len())
+            .min_by_key(|&i| deadline(pending[i].1))
+            .unwrap();
+
+        let smallest_deadlines = {
+            let mut deadlines: Vec<usize> = pending.iter()
+                .enumerate()
+                .filter(|&(i, _)| i != smallest)
+                .map(|(_, &(_, offset, _))| deadline(offset))
+                .collect();
+
+            if let HuffmanNode::Branch { ref node0, ref node1 } = *pending[smallest].0 {
+                for child in &[node0, node1] {
+                    if let HuffmanNode::Branch { .. } = ***child {
+                        deadlines.push(pair_index + 0x40);
+                    }
+                }
             }
-        } else {
-            unreachable!();
+
+            deadlines
         };
+
+        let index = if is_feasible(smallest_deadlines, pair_index + 1) { smallest } else { earliest };
+        let (node, offset, _) = pending.swap_remove(index);
+
+        if deadline(offset) < pair_index {
+            return Err(Error::InvalidArgument("huffman tree cannot be laid out"));
+        }
+
+        if let HuffmanNode::Branch { ref node0, ref node1 } = *node {
+            let node0_offset = tree_data.len();
+
+            tree_data[offset] = (pair_index - offset.div_ceil(2)) as u8;
+            tree_data.extend_from_slice(&[0, 0]);
+
+            for (i, child) in [&**node0, &**node1].iter().enumerate() {
+                match **child {
+                    HuffmanNode::Branch { .. } => {
+                        pending.push((*child, node0_offset + i, leaf_count(child)));
+                    },
+                    HuffmanNode::Leaf { value } => {
+                        tree_data[offset] |= 0x80 >> i;
+                        tree_data[node0_offset + i] = value;
+                    },
+                }
+            }
+        }
+    }
+
+    Ok(tree_data)
+}
+
+fn compress_huffman_symbols(input: &[u8], bit_length: u8, options: &CompressOptions) -> Result<Vec<u8>> {
+    let symbols: Vec<u8> = if bit_length == 8 {
+        input.to_vec()
+    } else {
+        let mut symbols: Vec<u8> = Vec::with_capacity(input.len() * 2);
+        for &byte in input {
+            symbols.push(byte & 0xF);
+            symbols.push(byte >> 4);
+        }
+        symbols
+    };
+
+    let mut frequencies = vec![0; 1 << bit_length];
+    for &symbol in &symbols {
+        frequencies[symbol as usize] += 1;
+    }
+
+    // When the optimal tree cannot be laid out within the offset limits the
+    // frequencies are flattened, trading a little compression for a shallower
+    // tree, until a valid layout is found.
+    let (huffman_tree, mut tree_data) = loop {
+        let huffman_tree = build_tree(&frequencies);
+
+        match write_tree(&huffman_tree) {
+            Ok(tree_data) => break (huffman_tree, tree_data),
+            Err(error) => {
+                if frequencies.iter().all(|&frequency| frequency <= 1) {
+                    return Err(error);
+                }
+
+                for frequency in frequencies.iter_mut() {
+                    *frequency = (*frequency).div_ceil(2);
+                }
+            },
+        }
+    };
+
+    let mut codes: Vec<Vec<bool>> = vec![Vec::new(); 1 << bit_length];
+    collect_codes(&huffman_tree, &mut Vec::new(), &mut codes);
+
+    let mut output: Vec<u8> = Vec::new();
+    let header = BiosHeader { kind: BiosCompressionType::Huffman, parameter: bit_length, size: input.len() };
+    header.write_into(&mut output, options)?;
+
+    // Padding the tree table keeps the bitstream 32-bit aligned
+    while (tree_data.len() + 1) % 4 != 0 {
+        tree_data.write_u8(0);
     }
 
-    assert_eq!(output.len(), decompressed_size);
+    output.write_u8(((tree_data.len() - 1) / 2) as u8);
+    output.extend_from_slice(&tree_data);
+
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+
+    for &symbol in &symbols {
+        for &bit in &codes[symbol as usize] {
+            bits = (bits << 1) | bit as u32;
+            bit_count += 1;
+
+            if bit_count == 32 {
+                output.write_u32_le(bits);
+                bits = 0;
+                bit_count = 0;
+            }
+        }
+    }
+
+    if bit_count > 0 {
+        output.write_u32_le(bits << (32 - bit_count));
+    }
+
+    pad_output(&mut output, options);
     Ok(output)
 }
 
+pub fn compress_huffman_with_options(input: &[u8], bit_length: HuffmanBitLength, options: &CompressOptions) -> Result<Vec<u8>> {
+    match bit_length {
+        HuffmanBitLength::Bits4 => compress_huffman_symbols(input, 4, options),
+        HuffmanBitLength::Bits8 => compress_huffman_symbols(input, 8, options),
+        HuffmanBitLength::Auto => {
+            let output_bits4 = compress_huffman_symbols(input, 4, options)?;
+            let output_bits8 = compress_huffman_symbols(input, 8, options)?;
+
+            if output_bits4.len() < output_bits8.len() {
+                Ok(output_bits4)
+            } else {
+                Ok(output_bits8)
+            }
+        },
+    }
+}
+
+pub fn compress_huffman_with_bit_length(input: &[u8], bit_length: HuffmanBitLength) -> Result<Vec<u8>> {
+    compress_huffman_with_options(input, bit_length, &CompressOptions::default())
+}
+
 pub fn compress_huffman(input: &[u8]) -> Result<Vec<u8>> {
-    Err(Error::new(ErrorKind::Other, "unimplemented"))
+    compress_huffman_with_bit_length(input, HuffmanBitLength::Bits8)
 }
@@ -1,96 +1,307 @@
-use std::io::{Cursor, Result, Error, ErrorKind};
-use std::cmp;
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use bios::{BiosCompressionType, bios_compression_type};
-use utils::same_count;
+use core::cmp;
+use alloc::vec::Vec;
+use bios::{BiosCompressionType, BiosHeader, consumed_length, allocate_output, check_padding, pad_output};
+use error::{Error, Result};
+use options::{check_output_size, CompressOptions, DecompressOptions};
+use utils::{same_count, ByteReader, WriteExt};
 
-pub fn decompress_lz77(input: &[u8]) -> Result<Vec<u8>> {
-    let mut cursor = Cursor::new(input);
+fn decompress_lz77_into_with_end(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<(usize, usize)> {
+    let mut reader = ByteReader::new(input);
+    let header = BiosHeader::read(&mut reader, options)?;
 
-    if bios_compression_type(cursor.read_u8()?) != Some(BiosCompressionType::Lz77) {
-        return Err(Error::new(ErrorKind::InvalidData, "compression header mismatch"));
+    if header.kind != BiosCompressionType::Lz77 {
+        return Err(Error::HeaderMismatch { input_offset: 0 });
     }
 
-    let decompressed_size: usize = cursor.read_u24::<LittleEndian>()? as usize;
-    let mut output = Vec::with_capacity(decompressed_size);
+    check_output_size(options, header.size)?;
+
+    let decompressed_size = header.size;
+    if decompressed_size > output.len() {
+        return Err(Error::SizeLimit { size: decompressed_size, limit: output.len() });
+    }
 
-    while output.len() < decompressed_size {
-        let block_types = cursor.read_u8()?;
+    let mut position = 0;
+
+    while position < decompressed_size {
+        let block_types = reader.read_u8(position)?;
 
         for i in 0..8 {
-            if output.len() < decompressed_size {
+            if position < decompressed_size {
                 if block_types & (0x80 >> i) == 0 {
                     // Uncompressed
-                    output.write_u8(cursor.read_u8()?)?;
+                    output[position] = reader.read_u8(position)?;
+                    position += 1;
                 } else {
                     // Reference
-                    let block = cursor.read_u16::<LittleEndian>()? as usize;
+                    let block_offset = reader.position();
+                    let block = reader.read_u16_le(position)? as usize;
                     let length = ((block >> 4) & 0xF) + 3;
                     let offset = (((block & 0xF) << 8) | ((block >> 8) & 0xFF)) + 1;
 
-                    if output.len() + length > decompressed_size {
-                        return Err(Error::new(ErrorKind::InvalidData, "length out of bounds"));
+                    if position + length > decompressed_size {
+                        return Err(Error::LengthOverrun { input_offset: block_offset, output_position: position });
                     }
 
-                    if offset > output.len() {
-                        return Err(Error::new(ErrorKind::InvalidData, "offset out of bounds"));
+                    if offset > position {
+                        return Err(Error::OffsetOutOfBounds { input_offset: block_offset, output_position: position });
                     }
 
                     for _ in 0..length {
-                        let index = output.len() - offset;
-                        let byte = output[index];
-                        output.write_u8(byte)?;
+                        output[position] = output[position - offset];
+                        position += 1;
                     }
                 }
             }
         }
     }
 
-    assert_eq!(output.len(), decompressed_size);
-    Ok(output)
+    check_padding(input, reader.position(), position, options)?;
+    Ok((position, reader.position()))
 }
 
-pub fn compress_lz77(input: &[u8], vram_safe: bool) -> Result<Vec<u8>> {
-    enum Block {
-        Uncompressed {
-            data: u8,
-        },
-        Reference {
-            offset: u16,
-            length: u8,
+pub fn decompress_lz77_into_with_options(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<usize> {
+    decompress_lz77_into_with_end(input, output, options).map(|(written, _)| written)
+}
+
+pub fn decompress_lz77_into(input: &[u8], output: &mut [u8]) -> Result<usize> {
+    decompress_lz77_into_with_options(input, output, &DecompressOptions::default())
+}
+
+pub fn decompress_lz77_with_end(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut output = allocate_output(input, options)?;
+    let (_, end) = decompress_lz77_into_with_end(input, &mut output, options)?;
+    Ok((output, end))
+}
+
+pub fn decompress_lz77_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    decompress_lz77_with_end(input, options).map(|(output, end)| (output, consumed_length(end, input)))
+}
+
+pub fn decompress_lz77_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    decompress_lz77_with_options(input, &DecompressOptions::default())
+}
+
+pub fn decompress_lz77(input: &[u8]) -> Result<Vec<u8>> {
+    decompress_lz77_with_length(input).map(|(output, _)| output)
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Lz77Level {
+    Greedy,
+    Lazy,
+    Optimal,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Lz77TieBreak {
+    Nearest,
+    Farthest,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[non_exhaustive]
+pub struct Lz77Options {
+    pub level: Lz77Level,
+    pub window_size: usize,
+    pub min_offset: usize,
+    pub tie_break: Lz77TieBreak,
+    pub max_chain_length: usize,
+    pub nice_length: usize,
+    pub vram_safe: bool,
+    pub compress_options: CompressOptions,
+}
+
+impl Default for Lz77Options {
+    fn default() -> Lz77Options {
+        Lz77Options {
+            level: Lz77Level::Greedy,
+            window_size: 4096,
+            min_offset: 1,
+            tie_break: Lz77TieBreak::Nearest,
+            max_chain_length: usize::MAX,
+            nice_length: 18,
+            vram_safe: false,
+            compress_options: CompressOptions::default(),
         }
     }
+}
 
-    let mut blocks: Vec<Block> = Vec::new();
-    let mut index = 0;
+pub enum Block {
+    Uncompressed {
+        data: u8,
+    },
+    Reference {
+        offset: u16,
+        length: u8,
+    }
+}
+
+pub struct MatchFinder<'a> {
+    input: &'a [u8],
+    // Previous position starting with the same 3-byte hash, or `usize::MAX`
+    previous: Vec<usize>,
+    min_offset: usize,
+    max_offset: usize,
+    tie_break: Lz77TieBreak,
+    // Optional bounds on the search, trading matches for speed on low-entropy
+    // data where the chains get long. By default the whole window is searched.
+    max_chain_length: usize,
+    nice_length: usize,
+}
+
+impl<'a> MatchFinder<'a> {
+    pub fn new(input: &'a [u8], options: &Lz77Options) -> MatchFinder<'a> {
+        fn hash(data: &[u8]) -> usize {
+            let key = ((data[0] as u32) << 16) | ((data[1] as u32) << 8) | (data[2] as u32);
+            (key.wrapping_mul(0x9E3779B1) >> 16) as usize
+        }
+
+        let mut heads: Vec<usize> = vec![usize::MAX; 0x10000];
+        let mut previous: Vec<usize> = vec![usize::MAX; input.len()];
+
+        for index in 0..input.len().saturating_sub(2) {
+            let head = &mut heads[hash(&input[index..])];
+            previous[index] = *head;
+            *head = index;
+        }
 
-    while index < input.len() {
         // When decompressing to VRAM the previous byte cannot be referenced in
         // the uncompressed data because it may have not written to the memory yet.
         // The data to the VRAM is written in 16-bit words due to 16-bit data bus.
-        let min_offset = if vram_safe { 2 } else { 1 };
-        let max_offset = cmp::min(index, 4096);
+        let min_offset = if options.vram_safe { cmp::max(options.min_offset, 2) } else { options.min_offset };
+
+        MatchFinder {
+            input,
+            previous,
+            min_offset,
+            max_offset: options.window_size,
+            tie_break: options.tie_break,
+            max_chain_length: options.max_chain_length,
+            nice_length: options.nice_length,
+        }
+    }
+
+    fn find_longest_match(&self, index: usize) -> Option<(usize, usize)> {
+        let max_offset = cmp::min(index, self.max_offset);
 
         let min_length = 3;
-        let max_length = cmp::min(input.len() - index, 18);
+        let max_length = cmp::min(self.input.len() - index, 18);
+
+        if max_length < min_length {
+            return None;
+        }
 
         let mut best_reference: Option<(usize, usize)> = None;
+        let mut chain_length = 0;
+
+        // The chain visits earlier positions nearest first
+        let mut candidate = self.previous[index];
+        while (candidate != usize::MAX) && (index - candidate <= max_offset) && (chain_length < self.max_chain_length) {
+            let current_offset = index - candidate;
+            chain_length += 1;
+
+            // A candidate can only beat the best match if it agrees on the byte
+            // deciding the comparison, which rules most of them out cheaply.
+            let may_improve = match best_reference {
+                Some((_, best_length)) => {
+                    let position = match self.tie_break {
+                        Lz77TieBreak::Nearest => best_length,
+                        Lz77TieBreak::Farthest => best_length - 1,
+                    };
+                    (position < max_length) && (self.input[candidate + position] == self.input[index + position])
+                },
+                None => true,
+            };
+
+            if (current_offset >= self.min_offset) && may_improve {
+                let current_length = same_count(&self.input[index..], &self.input[candidate..], max_length);
 
-        for current_offset in min_offset..=max_offset {
-            let current_length = same_count(&input[index..], &input[index - current_offset..], max_length);
+                if current_length >= min_length {
+                    if let Some((_, best_length)) = best_reference {
+                        let is_better = match self.tie_break {
+                            Lz77TieBreak::Nearest => current_length > best_length,
+                            Lz77TieBreak::Farthest => current_length >= best_length,
+                        };
 
-            if current_length >= min_length {
-                if let Some((_, best_length)) = best_reference {
-                    if current_length > best_length {
+                        if is_better {
+                            best_reference = Some((current_offset, current_length));
+                        }
+                    } else {
                         best_reference = Some((current_offset, current_length));
                     }
-                } else {
-                    best_reference = Some((current_offset, current_length));
+
+                    // A farther match of the same length may still follow, so
+                    // only the nearest match can stop the walk early.
+                    if (self.tie_break == Lz77TieBreak::Nearest) && (current_length >= cmp::min(self.nice_length, max_length)) {
+                        break;
+                    }
                 }
             }
+
+            candidate = self.previous[candidate];
+        }
+
+        best_reference
+    }
+}
+
+// Writes up to eight blocks behind their shared flag byte, where `index` is
+// the position in the uncompressed data the first block starts at.
+pub fn write_block_group(output: &mut Vec<u8>, blocks: &[Block], index: &mut usize) -> Result<()> {
+    let mut block_types = 0;
+    for (i, block) in blocks.iter().enumerate() {
+        if let Block::Reference { .. } = *block  {
+            block_types |= 0x80 >> i;
+        }
+    }
+    output.write_u8(block_types);
+
+    for block in blocks {
+        match *block {
+            Block::Uncompressed { data } => {
+                output.write_u8(data);
+                *index += 1;
+            },
+            Block::Reference { offset, length } => {
+                if !(3..=18).contains(&length) {
+                    return Err(Error::LengthOverrun { input_offset: *index, output_position: output.len() });
+                }
+
+                if !(1..=4096).contains(&offset) || (offset as usize > *index) {
+                    return Err(Error::OffsetOutOfBounds { input_offset: *index, output_position: output.len() });
+                }
+
+                let data = (((offset - 1) & 0xFF) << 8) | ((length - 3) << 4) as u16 | ((offset - 1) >> 8);
+                output.write_u16_le(data);
+                *index += length as usize;
+            },
         }
+    }
 
-        if let Some((best_offset, best_length)) = best_reference {
+    Ok(())
+}
+
+fn write_blocks(input: &[u8], blocks: &[Block], options: &CompressOptions) -> Result<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::new();
+    let header = BiosHeader { kind: BiosCompressionType::Lz77, parameter: 0, size: input.len() };
+    header.write_into(&mut output, options)?;
+
+    let mut index = 0;
+    for chunk in blocks.chunks(8) {
+        write_block_group(&mut output, chunk, &mut index)?;
+    }
+
+    pad_output(&mut output, options);
+    Ok(output)
+}
+
+// The greedy and lazy parsers stop at `end`, so that longer inputs can be
+// parsed piece by piece with the preceding data kept around for references.
+fn parse_greedy(input: &[u8], match_finder: &MatchFinder, blocks: &mut Vec<Block>, start: usize, end: usize) -> usize {
+    let mut index = start;
+
+    while index < end {
+        if let Some((best_offset, best_length)) = match_finder.find_longest_match(index) {
             blocks.push(Block::Reference {
                 offset: best_offset as u16,
                 length: best_length as u8,
@@ -102,34 +313,154 @@ pub fn compress_lz77(input: &[u8], vram_safe: bool) -> Result<Vec<u8>> {
         }
     }
 
-    let mut output = Vec::new();
-    output.write_u8((BiosCompressionType::Lz77 as u8) << 4)?;
-    output.write_u24::<LittleEndian>(input.len() as u32)?;
+    index
+}
 
-    for chunk in blocks.chunks(8) {
-        let mut block_types = 0;
-        for (i, block) in chunk.iter().enumerate() {
-            if let Block::Reference { .. } = *block  {
-                block_types |= 0x80 >> i;
-            }
+fn parse_lazy(input: &[u8], match_finder: &MatchFinder, blocks: &mut Vec<Block>, start: usize, end: usize) -> usize {
+    let mut index = start;
+
+    while index < end {
+        let current_match = match_finder.find_longest_match(index);
+
+        // Emitting a literal first only pays off when the literal and the match at
+        // the next position cost less per byte than the current match does. With
+        // the flag bits, literals take 9 bits and references 17.
+        let is_deferred = match current_match {
+            Some((_, current_length)) => match match_finder.find_longest_match(index + 1) {
+                Some((_, next_length)) => (9 + 17) * current_length < 17 * (next_length + 1),
+                None => false,
+            },
+            None => false,
+        };
+
+        match current_match {
+            Some((best_offset, best_length)) if !is_deferred => {
+                blocks.push(Block::Reference {
+                    offset: best_offset as u16,
+                    length: best_length as u8,
+                });
+                index += best_length;
+            },
+            _ => {
+                blocks.push(Block::Uncompressed { data: input[index] });
+                index += 1;
+            },
         }
-        output.write_u8(block_types)?;
+    }
 
-        for block in chunk {
-            match *block {
-                Block::Uncompressed { data } => {
-                    output.write_u8(data)?;
-                },
-                Block::Reference { offset, length } => {
-                    assert!((length >= 3) & (length <= 18), "length out of bounds");
-                    assert!((offset >= 1) & (offset <= 4096), "offset out of bounds");
+    index
+}
 
-                    let data = (((offset - 1) & 0xFF) << 8) | ((length - 3) << 4) as u16 | ((offset - 1) >> 8);
-                    output.write_u16::<LittleEndian>(data)?;
-                },
+pub fn parse_range(input: &[u8], options: &Lz77Options, blocks: &mut Vec<Block>, start: usize, end: usize) -> usize {
+    let match_finder = MatchFinder::new(input, options);
+
+    match options.level {
+        Lz77Level::Greedy => parse_greedy(input, &match_finder, blocks, start, end),
+        Lz77Level::Lazy => parse_lazy(input, &match_finder, blocks, start, end),
+        Lz77Level::Optimal => {
+            // The optimal parse needs the whole input at once
+            blocks.extend(parse_optimal(input, &match_finder));
+            input.len()
+        },
+    }
+}
+
+fn parse_optimal(input: &[u8], match_finder: &MatchFinder) -> Vec<Block> {
+    let matches: Vec<Option<(usize, usize)>> = (0..input.len())
+        .map(|index| match_finder.find_longest_match(index))
+        .collect();
+
+    // Every eight blocks share a flag byte, so the cost of the remaining data
+    // depends on how many blocks of the current flag byte are already used.
+    // `costs` only keeps the rows reachable by the longest reference.
+    let mut costs: Vec<[usize; 8]> = vec![[0; 8]; 19];
+    let mut lengths: Vec<[u8; 8]> = vec![[0; 8]; input.len()];
+
+    for index in (0..input.len()).rev() {
+        for phase in 0..8 {
+            let next_phase = (phase + 1) % 8;
+            let flag_cost = if phase == 0 { 1 } else { 0 };
+
+            let mut best_cost = 1 + costs[(index + 1) % 19][next_phase];
+            let mut best_length = 1;
+
+            if let Some((_, max_length)) = matches[index] {
+                for length in 3..=max_length {
+                    let cost = 2 + costs[(index + length) % 19][next_phase];
+                    if cost < best_cost {
+                        best_cost = cost;
+                        best_length = length;
+                    }
+                }
             }
+
+            costs[index % 19][phase] = flag_cost + best_cost;
+            lengths[index][phase] = best_length as u8;
         }
     }
 
-    Ok(output)
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut index = 0;
+
+    while index < input.len() {
+        let length = lengths[index][blocks.len() % 8] as usize;
+
+        if length == 1 {
+            blocks.push(Block::Uncompressed { data: input[index] });
+        } else {
+            let (offset, _) = matches[index].unwrap();
+            blocks.push(Block::Reference {
+                offset: offset as u16,
+                length: length as u8,
+            });
+        }
+
+        index += length;
+    }
+
+    blocks
+}
+
+pub fn check_options(options: &Lz77Options) -> Result<()> {
+    if (options.window_size < 1) || (options.window_size > 4096) {
+        return Err(Error::InvalidArgument("window size out of bounds"));
+    }
+
+    if (options.min_offset < 1) || (options.min_offset > options.window_size) {
+        return Err(Error::InvalidArgument("minimum offset out of bounds"));
+    }
+
+    if options.max_chain_length < 1 {
+        return Err(Error::InvalidArgument("maximum chain length out of bounds"));
+    }
+
+    if (options.nice_length < 3) || (options.nice_length > 18) {
+        return Err(Error::InvalidArgument("nice length out of bounds"));
+    }
+
+    Ok(())
+}
+
+pub fn compress_lz77_with_options(input: &[u8], options: &Lz77Options) -> Result<Vec<u8>> {
+    check_options(options)?;
+
+    let mut blocks: Vec<Block> = Vec::new();
+    parse_range(input, options, &mut blocks, 0, input.len());
+
+    write_blocks(input, &blocks, &options.compress_options)
+}
+
+pub fn compress_lz77(input: &[u8], vram_safe: bool) -> Result<Vec<u8>> {
+    compress_lz77_with_options(input, &Lz77Options {
+        vram_safe,
+        ..Lz77Options::default()
+    })
+}
+
+pub fn compress_lz77_optimal(input: &[u8], vram_safe: bool) -> Result<Vec<u8>> {
+    compress_lz77_with_options(input, &Lz77Options {
+        level: Lz77Level::Optimal,
+        vram_safe,
+        ..Lz77Options::default()
+    })
 }
@@ -1,29 +1,250 @@
 #[cfg(test)]
 mod tests;
 
+mod chain;
 mod diff;
 mod huffman;
 mod lz77;
 mod rle;
+#[cfg(feature = "std")]
+mod stream;
 
-pub use self::diff::{filter_diff8, unfilter_diff8};
-pub use self::diff::{filter_diff16, unfilter_diff16};
-pub use self::huffman::{compress_huffman, decompress_huffman};
-pub use self::lz77::{compress_lz77, decompress_lz77};
-pub use self::rle::{compress_rle, decompress_rle};
+pub use self::chain::{compress_best, compress_chain, decompress_chain, decompress_chain_with_options, BiosMethod};
+pub use self::chain::{compress_best_with_options, compress_chain_with_options};
+pub use self::chain::{decompress_chain_exact, decompress_chain_exact_with_options};
+pub use self::diff::{filter_diff8, unfilter_diff8, unfilter_diff8_into, unfilter_diff8_with_length};
+pub use self::diff::{filter_diff16, unfilter_diff16, unfilter_diff16_into, unfilter_diff16_with_length};
+pub use self::diff::{filter_diff8_with_options, filter_diff16_with_options};
+pub use self::diff::{unfilter_diff8_with_options, unfilter_diff16_with_options};
+pub use self::diff::{unfilter_diff8_into_with_options, unfilter_diff16_into_with_options};
+pub use self::diff::{unfilter_diff_with_length, unfilter_diff_with_options};
+pub use self::huffman::{compress_huffman, compress_huffman_with_bit_length, compress_huffman_with_options, HuffmanBitLength};
+pub use self::huffman::{decompress_huffman, decompress_huffman_into, decompress_huffman_with_length};
+pub use self::huffman::{decompress_huffman_into_with_options, decompress_huffman_with_options};
+pub use self::lz77::{compress_lz77, compress_lz77_optimal, compress_lz77_with_options};
+pub use self::lz77::{decompress_lz77, decompress_lz77_into, decompress_lz77_with_length};
+pub use self::lz77::{decompress_lz77_into_with_options, decompress_lz77_with_options};
+pub use self::lz77::{Lz77Level, Lz77Options, Lz77TieBreak};
+pub use self::rle::{compress_rle, decompress_rle, decompress_rle_into, decompress_rle_with_length};
+pub use self::rle::{compress_rle_with_options, decompress_rle_into_with_options, decompress_rle_with_options};
+pub use self::rle::{compress_rle_optimal, compress_rle_optimal_with_options};
+#[cfg(feature = "std")]
+pub use self::stream::{Lz77Decoder, Lz77Encoder, RleEncoder};
 
-use num::FromPrimitive;
+use self::diff::unfilter_diff_with_end;
+use self::huffman::decompress_huffman_with_end;
+use self::lz77::decompress_lz77_with_end;
+use self::rle::decompress_rle_with_end;
 
-enum_from_primitive! {
-    #[derive(Debug, Eq, PartialEq)]
-    pub enum BiosCompressionType {
-        Lz77       = 1,
-        Huffman    = 2,
-        Rle        = 3,
-        DiffFilter = 8,
+use core::cmp;
+use core::convert::TryFrom;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+use error::{Error, Result};
+use options::{check_output_size, CompressOptions, DecompressOptions};
+use utils::{ByteReader, WriteExt};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum BiosCompressionType {
+    Lz77       = 1,
+    Huffman    = 2,
+    Rle        = 3,
+    DiffFilter = 8,
+}
+
+impl TryFrom<u8> for BiosCompressionType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<BiosCompressionType> {
+        match value {
+            1 => Ok(BiosCompressionType::Lz77),
+            2 => Ok(BiosCompressionType::Huffman),
+            3 => Ok(BiosCompressionType::Rle),
+            8 => Ok(BiosCompressionType::DiffFilter),
+            _ => Err(Error::HeaderMismatch { input_offset: 0 }),
+        }
     }
 }
 
 pub fn bios_compression_type(value: u8) -> Option<BiosCompressionType> {
-    BiosCompressionType::from_u8(value >> 4)
+    BiosCompressionType::try_from(value >> 4).ok()
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct BiosHeader {
+    pub kind: BiosCompressionType,
+    pub parameter: u8,
+    pub size: usize,
+}
+
+impl BiosHeader {
+    pub fn parse(input: &[u8]) -> Result<BiosHeader> {
+        BiosHeader::parse_with_options(input, &DecompressOptions::default())
+    }
+
+    pub fn parse_with_options(input: &[u8], options: &DecompressOptions) -> Result<BiosHeader> {
+        BiosHeader::read(&mut ByteReader::new(input), options)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_with_options(writer, &CompressOptions::default())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_with_options<W: Write>(&self, writer: &mut W, options: &CompressOptions) -> io::Result<()> {
+        writer.write_all(&self.to_bytes(options)?)
+    }
+
+    pub fn to_bytes(&self, options: &CompressOptions) -> Result<Vec<u8>> {
+        let mut output: Vec<u8> = Vec::new();
+        self.write_into(&mut output, options)?;
+        Ok(output)
+    }
+
+    fn is_valid_parameter(&self) -> bool {
+        match self.kind {
+            BiosCompressionType::Lz77 | BiosCompressionType::Rle => self.parameter == 0,
+            BiosCompressionType::Huffman => (self.parameter == 4) || (self.parameter == 8),
+            BiosCompressionType::DiffFilter => (self.parameter == 1) || (self.parameter == 2),
+        }
+    }
+
+    fn read(reader: &mut ByteReader, options: &DecompressOptions) -> Result<BiosHeader> {
+        let value = reader.read_u8(0)?;
+        let kind = bios_compression_type(value).ok_or(Error::HeaderMismatch { input_offset: 0 })?;
+        let mut size = reader.read_u24_le(0)? as usize;
+
+        // Extended headers, as used by NDS tooling, store a zero size
+        // followed by the actual size in 32 bits.
+        if (size == 0) && options.extended_header {
+            size = reader.read_u32_le(0)? as usize;
+        }
+
+        let header = BiosHeader { kind, parameter: value & 0xF, size };
+        if !header.is_valid_parameter() {
+            return Err(Error::HeaderMismatch { input_offset: 0 });
+        }
+
+        Ok(header)
+    }
+
+    fn write_into(&self, output: &mut Vec<u8>, options: &CompressOptions) -> Result<()> {
+        if !self.is_valid_parameter() {
+            return Err(Error::InvalidArgument("invalid header parameter"));
+        }
+
+        // The extended form is only used when the GBA header cannot represent
+        // the size, or would be ambiguous with it.
+        let extended = options.extended_header && ((self.size == 0) || (self.size > 0xFFFFFF));
+        let limit = if options.extended_header { 0xFFFFFFFF } else { 0xFFFFFF };
+
+        if self.size as u64 > limit {
+            return Err(Error::SizeLimit { size: self.size, limit: limit as usize });
+        }
+
+        output.write_u8(((self.kind as u8) << 4) | self.parameter);
+
+        if extended {
+            output.write_u24_le(0);
+            output.write_u32_le(self.size as u32);
+        } else {
+            output.write_u24_le(self.size as u32);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn peek_decompressed_size(input: &[u8]) -> Result<usize> {
+    BiosHeader::parse(input).map(|header| header.size)
+}
+
+// The header is returned as well, since the parameter tells apart settings
+// like the Huffman symbol width that are needed to encode the data again.
+pub fn decompress_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize, BiosHeader)> {
+    decompress_with_end(input, options).map(|(output, end, header)| (output, consumed_length(end, input), header))
+}
+
+pub fn decompress_with_length(input: &[u8]) -> Result<(Vec<u8>, usize, BiosHeader)> {
+    decompress_with_options(input, &DecompressOptions::default())
+}
+
+pub fn decompress(input: &[u8]) -> Result<(Vec<u8>, BiosHeader)> {
+    decompress_with_length(input).map(|(output, _, header)| (output, header))
+}
+
+// The end is the exact input position where the stream stopped, before any
+// alignment padding.
+fn decompress_with_end(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize, BiosHeader)> {
+    let header = BiosHeader::parse_with_options(input, options)?;
+
+    let (output, end) = match header.kind {
+        BiosCompressionType::Lz77 => decompress_lz77_with_end(input, options)?,
+        BiosCompressionType::Huffman => decompress_huffman_with_end(input, options)?,
+        BiosCompressionType::Rle => decompress_rle_with_end(input, options)?,
+        BiosCompressionType::DiffFilter => unfilter_diff_with_end(input, options)?,
+    };
+
+    Ok((output, end, header))
+}
+
+// The BIOS requires compressed data to be 32-bit aligned, so the padding
+// following a stream counts as consumed as long as it is present.
+fn consumed_length(position: usize, input: &[u8]) -> usize {
+    cmp::min((position + 3) & !3, input.len())
+}
+
+// Unless padding is requested it is left to the caller, since streams are
+// just as often followed directly by other data.
+fn pad_output(output: &mut Vec<u8>, options: &CompressOptions) {
+    if options.alignment_padding {
+        while output.len() % 4 != 0 {
+            output.write_u8(0);
+        }
+    }
+}
+
+fn check_padding(input: &[u8], position: usize, output_position: usize, options: &DecompressOptions) -> Result<()> {
+    if options.alignment_padding {
+        for input_offset in position..((position + 3) & !3) {
+            match input.get(input_offset) {
+                Some(0) => {},
+                Some(_) => return Err(Error::InvalidPadding { input_offset }),
+                None => return Err(Error::TruncatedInput { input_offset, output_position }),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The most output a single input byte can decode to, which is reached by
+// LZ77 references of 18 bytes, RLE runs of 130 bytes and one bit Huffman codes.
+fn max_expansion(kind: BiosCompressionType) -> usize {
+    match kind {
+        BiosCompressionType::Lz77 => 9,
+        BiosCompressionType::Huffman => 8,
+        BiosCompressionType::Rle => 65,
+        BiosCompressionType::DiffFilter => 1,
+    }
+}
+
+// The declared size is checked before allocating, so that garbage headers
+// cannot request megabytes of output. Sizes the input could never decode to
+// are rejected even without a limit.
+fn allocate_output(input: &[u8], options: &DecompressOptions) -> Result<Vec<u8>> {
+    let header = BiosHeader::parse_with_options(input, options)?;
+    check_output_size(options, header.size)?;
+
+    let limit = input.len().saturating_mul(max_expansion(header.kind));
+    if header.size > limit {
+        return Err(Error::SizeLimit { size: header.size, limit });
+    }
+
+    let mut output = Vec::new();
+    output.try_reserve_exact(header.size).map_err(|_| Error::SizeLimit { size: header.size, limit: isize::MAX as usize })?;
+    output.resize(header.size, 0);
+    Ok(output)
 }
@@ -1,67 +1,178 @@
-use std::io::{Write, Cursor, Result, Error, ErrorKind};
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use bios::{BiosCompressionType, bios_compression_type};
-use utils::{consecutive_count, non_consecutive_count};
+use core::cmp;
+use alloc::vec::Vec;
+use bios::{BiosCompressionType, BiosHeader, consumed_length, allocate_output, check_padding, pad_output};
+use error::{Error, Result};
+use options::{check_output_size, CompressOptions, DecompressOptions};
+use utils::{ByteReader, RunLengths, WriteExt};
 
-pub fn decompress_rle(input: &[u8]) -> Result<Vec<u8>> {
-    let mut cursor = Cursor::new(input);
+fn decompress_rle_into_with_end(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<(usize, usize)> {
+    let mut reader = ByteReader::new(input);
+    let header = BiosHeader::read(&mut reader, options)?;
+
+    if header.kind != BiosCompressionType::Rle {
+        return Err(Error::HeaderMismatch { input_offset: 0 });
+    }
 
-    if bios_compression_type(cursor.read_u8()?) != Some(BiosCompressionType::Rle) {
-        return Err(Error::new(ErrorKind::InvalidData, "compression header mismatch"));
+    check_output_size(options, header.size)?;
+
+    let decompressed_size = header.size;
+    if decompressed_size > output.len() {
+        return Err(Error::SizeLimit { size: decompressed_size, limit: output.len() });
     }
 
-    let decompressed_size: usize = cursor.read_u24::<LittleEndian>()? as usize;
-    let mut output: Vec<u8> = Vec::with_capacity(decompressed_size);
+    let mut position = 0;
 
-    while output.len() < decompressed_size {
-        let block = cursor.read_u8()? as usize;
+    while position < decompressed_size {
+        let block_offset = reader.position();
+        let block = reader.read_u8(position)? as usize;
         if block & 0x80 == 0 {
             // Uncompressed
             let length = (block & 0x7F) + 1;
-            if output.len() + length > decompressed_size {
-                return Err(Error::new(ErrorKind::InvalidData, "length out of bounds"));
+            if position + length > decompressed_size {
+                return Err(Error::LengthOverrun { input_offset: block_offset, output_position: position });
             }
 
             for _ in 0..length {
-                output.write_u8(cursor.read_u8()?)?;
+                output[position] = reader.read_u8(position)?;
+                position += 1;
             }
         } else {
             // Run-length encoded
             let length = (block & 0x7F) + 3;
-            if output.len() + length > decompressed_size {
-                return Err(Error::new(ErrorKind::InvalidData, "length out of bounds"));
+            if position + length > decompressed_size {
+                return Err(Error::LengthOverrun { input_offset: block_offset, output_position: position });
             }
 
-            let data = cursor.read_u8()?;
+            let data = reader.read_u8(position)?;
             for _ in 0..length {
-                output.write_u8(data)?;
+                output[position] = data;
+                position += 1;
             }
         }
     }
 
-    assert_eq!(output.len(), decompressed_size);
+    check_padding(input, reader.position(), position, options)?;
+    Ok((position, reader.position()))
+}
+
+pub fn decompress_rle_into_with_options(input: &[u8], output: &mut [u8], options: &DecompressOptions) -> Result<usize> {
+    decompress_rle_into_with_end(input, output, options).map(|(written, _)| written)
+}
+
+pub fn decompress_rle_into(input: &[u8], output: &mut [u8]) -> Result<usize> {
+    decompress_rle_into_with_options(input, output, &DecompressOptions::default())
+}
+
+pub fn decompress_rle_with_end(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut output = allocate_output(input, options)?;
+    let (_, end) = decompress_rle_into_with_end(input, &mut output, options)?;
+    Ok((output, end))
+}
+
+pub fn decompress_rle_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    decompress_rle_with_end(input, options).map(|(output, end)| (output, consumed_length(end, input)))
+}
+
+pub fn decompress_rle_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    decompress_rle_with_options(input, &DecompressOptions::default())
+}
+
+pub fn decompress_rle(input: &[u8]) -> Result<Vec<u8>> {
+    decompress_rle_with_length(input).map(|(output, _)| output)
+}
+
+pub fn compress_rle_with_options(input: &[u8], options: &CompressOptions) -> Result<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::new();
+    let header = BiosHeader { kind: BiosCompressionType::Rle, parameter: 0, size: input.len() };
+    header.write_into(&mut output, options)?;
+    write_rle_blocks(input, input.len(), &mut output);
+
+    pad_output(&mut output, options);
     Ok(output)
 }
 
-pub fn compress_rle(input: &[u8]) -> Result<Vec<u8>> {
-    let mut output = Vec::new();
-    output.write_u8((BiosCompressionType::Rle as u8) << 4)?;
-    output.write_u24::<LittleEndian>(input.len() as u32)?;
+// Encodes blocks starting before `end` and returns the offset the last one
+// stopped at. A block never looks further than 0x82 bytes ahead of its start.
+pub fn write_rle_blocks(input: &[u8], end: usize, output: &mut Vec<u8>) -> usize {
+    let run_lengths = RunLengths::new(input);
 
     let mut offset = 0;
-    while offset < input.len() {
-        let length = consecutive_count(&input[offset..], 0x82);
+    while offset < end {
+        let length = run_lengths.consecutive_count(offset, 0x82);
         if length < 3 {
-            let length = non_consecutive_count(&input[offset..], 0x80, 3);
-            output.write_u8(length as u8 - 1)?;
-            output.write_all(&input[offset..offset+length])?;
+            let length = run_lengths.non_consecutive_count(offset, 0x80, 3);
+            output.write_u8(length as u8 - 1);
+            output.extend_from_slice(&input[offset..offset+length]);
             offset += length;
         } else {
-            output.write_u8(0x80 | (length as u8 - 3))?;
-            output.write_u8(input[offset])?;
+            output.write_u8(0x80 | (length as u8 - 3));
+            output.write_u8(input[offset]);
             offset += length;
         }
     }
 
+    offset
+}
+
+pub fn compress_rle(input: &[u8]) -> Result<Vec<u8>> {
+    compress_rle_with_options(input, &CompressOptions::default())
+}
+
+pub fn compress_rle_optimal_with_options(input: &[u8], options: &CompressOptions) -> Result<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::new();
+    let header = BiosHeader { kind: BiosCompressionType::Rle, parameter: 0, size: input.len() };
+    header.write_into(&mut output, options)?;
+
+    let run_lengths = RunLengths::new(input);
+
+    // Shortest path over the positions, where `costs[i]` is the smallest
+    // encoded size of `input[..i]` and `last_blocks[i]` the (length, is_run)
+    // of the block ending there. Ties go to the later start, which keeps the
+    // leading blocks as long as possible.
+    let mut costs: Vec<usize> = vec![usize::MAX; input.len() + 1];
+    let mut last_blocks: Vec<(usize, bool)> = vec![(0, false); input.len() + 1];
+    costs[0] = 0;
+
+    for index in 0..input.len() {
+        let cost = costs[index];
+
+        for length in 1..=cmp::min(input.len() - index, 128) {
+            if cost + 1 + length <= costs[index + length] {
+                costs[index + length] = cost + 1 + length;
+                last_blocks[index + length] = (length, false);
+            }
+        }
+
+        for length in 3..=run_lengths.consecutive_count(index, 130) {
+            if cost + 2 <= costs[index + length] {
+                costs[index + length] = cost + 2;
+                last_blocks[index + length] = (length, true);
+            }
+        }
+    }
+
+    let mut blocks: Vec<(usize, usize, bool)> = Vec::new();
+    let mut offset = input.len();
+    while offset > 0 {
+        let (length, is_run) = last_blocks[offset];
+        offset -= length;
+        blocks.push((offset, length, is_run));
+    }
+
+    for &(offset, length, is_run) in blocks.iter().rev() {
+        if is_run {
+            output.write_u8(0x80 | (length as u8 - 3));
+            output.write_u8(input[offset]);
+        } else {
+            output.write_u8(length as u8 - 1);
+            output.extend_from_slice(&input[offset..offset+length]);
+        }
+    }
+
+    pad_output(&mut output, options);
     Ok(output)
 }
+
+pub fn compress_rle_optimal(input: &[u8]) -> Result<Vec<u8>> {
+    compress_rle_optimal_with_options(input, &CompressOptions::default())
+}
@@ -0,0 +1,358 @@
+use std::io::{self, Read, Write};
+use bios::{BiosCompressionType, BiosHeader, Lz77Level, Lz77Options};
+use bios::lz77::{check_options, parse_range, write_block_group, Block};
+use bios::rle::write_rle_blocks;
+use error::Error;
+use options::{check_output_size, CompressOptions, DecompressOptions};
+
+// References reach back at most 4096 bytes, so only that much of the output
+// has to be kept around.
+const WINDOW_SIZE: usize = 4096;
+
+// The encoders hold input back until this much has been buffered, plus enough
+// lookahead for the longest token, so that their output is identical to the
+// one of the in-memory compressors.
+const CHUNK_SIZE: usize = 0x10000;
+const LZ77_LOOKAHEAD: usize = 19;
+const RLE_LOOKAHEAD: usize = 0x82;
+
+pub struct Lz77Decoder<R: Read> {
+    reader: R,
+    window: Box<[u8; WINDOW_SIZE]>,
+    decompressed_size: usize,
+    input_position: usize,
+    position: usize,
+    block_types: u8,
+    remaining_blocks: usize,
+    reference_offset: usize,
+    reference_length: usize,
+    alignment_padding: bool,
+}
+
+impl<R: Read> Lz77Decoder<R> {
+    pub fn new(reader: R) -> io::Result<Lz77Decoder<R>> {
+        Lz77Decoder::with_options(reader, &DecompressOptions::default())
+    }
+
+    pub fn with_options(mut reader: R, options: &DecompressOptions) -> io::Result<Lz77Decoder<R>> {
+        let mut header = [0; 8];
+        let mut header_length = 4;
+
+        read_exact(&mut reader, &mut header[..4], 0, 0)?;
+        if options.extended_header && (header[1..4] == [0, 0, 0]) {
+            read_exact(&mut reader, &mut header[4..], 4, 0)?;
+            header_length = 8;
+        }
+
+        let header = BiosHeader::parse_with_options(&header[..header_length], options)?;
+        if header.kind != BiosCompressionType::Lz77 {
+            return Err(Error::HeaderMismatch { input_offset: 0 }.into());
+        }
+
+        check_output_size(options, header.size)?;
+
+        Ok(Lz77Decoder {
+            reader,
+            window: Box::new([0; WINDOW_SIZE]),
+            decompressed_size: header.size,
+            input_position: header_length,
+            position: 0,
+            block_types: 0,
+            remaining_blocks: 0,
+            reference_offset: 0,
+            reference_length: 0,
+            alignment_padding: options.alignment_padding,
+        })
+    }
+
+    pub fn decompressed_size(&self) -> usize {
+        self.decompressed_size
+    }
+
+    pub fn input_position(&self) -> usize {
+        self.input_position
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_bytes(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        read_exact(&mut self.reader, buffer, self.input_position, self.position)?;
+        self.input_position += buffer.len();
+        Ok(())
+    }
+
+    fn next_block(&mut self) -> io::Result<Option<u8>> {
+        if self.remaining_blocks == 0 {
+            let mut block_types = [0; 1];
+            self.read_bytes(&mut block_types)?;
+            self.block_types = block_types[0];
+            self.remaining_blocks = 8;
+        }
+
+        let is_reference = self.block_types & 0x80 != 0;
+        self.block_types <<= 1;
+        self.remaining_blocks -= 1;
+
+        if !is_reference {
+            // Uncompressed
+            let mut data = [0; 1];
+            self.read_bytes(&mut data)?;
+            return Ok(Some(data[0]));
+        }
+
+        // Reference
+        let block_offset = self.input_position;
+        let mut block = [0; 2];
+        self.read_bytes(&mut block)?;
+
+        let length = ((block[0] >> 4) as usize) + 3;
+        let offset = ((((block[0] & 0xF) as usize) << 8) | block[1] as usize) + 1;
+
+        if self.position + length > self.decompressed_size {
+            return Err(Error::LengthOverrun { input_offset: block_offset, output_position: self.position }.into());
+        }
+
+        if offset > self.position {
+            return Err(Error::OffsetOutOfBounds { input_offset: block_offset, output_position: self.position }.into());
+        }
+
+        self.reference_offset = offset;
+        self.reference_length = length;
+        Ok(None)
+    }
+
+    // Once the padding has been read the position is aligned, so calling this
+    // again does not read anything further.
+    fn skip_padding(&mut self) -> io::Result<()> {
+        let input_offset = self.input_position;
+        let mut padding = [0; 3];
+        let padding = &mut padding[..((input_offset + 3) & !3) - input_offset];
+        self.read_bytes(padding)?;
+
+        match padding.iter().position(|&data| data != 0) {
+            Some(i) => Err(Error::InvalidPadding { input_offset: input_offset + i }.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> Read for Lz77Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // The padding is checked by the read reporting the end of the data, so
+        // that errors do not swallow the bytes decoded before them.
+        if (self.position == self.decompressed_size) && self.alignment_padding {
+            self.skip_padding()?;
+        }
+
+        let mut written = 0;
+
+        while (written < buf.len()) && (self.position < self.decompressed_size) {
+            let data = if self.reference_length > 0 {
+                self.reference_length -= 1;
+                self.window[(self.position - self.reference_offset) % WINDOW_SIZE]
+            } else {
+                match self.next_block()? {
+                    Some(data) => data,
+                    None => continue,
+                }
+            };
+
+            self.window[self.position % WINDOW_SIZE] = data;
+            self.position += 1;
+
+            buf[written] = data;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+}
+
+fn read_exact<R: Read>(reader: &mut R, buffer: &mut [u8], input_offset: usize, output_position: usize) -> io::Result<()> {
+    reader.read_exact(buffer).map_err(|error| {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            Error::TruncatedInput { input_offset, output_position }.into()
+        } else {
+            error
+        }
+    })
+}
+
+pub struct Lz77Encoder<W: Write> {
+    writer: W,
+    options: Lz77Options,
+    size: usize,
+    written: usize,
+    // The window followed by the input that has not been parsed yet
+    buffer: Vec<u8>,
+    parsed: usize,
+    blocks: Vec<Block>,
+    position: usize,
+    output_length: usize,
+}
+
+impl<W: Write> Lz77Encoder<W> {
+    pub fn new(writer: W, size: usize) -> io::Result<Lz77Encoder<W>> {
+        Lz77Encoder::with_options(writer, size, &Lz77Options::default())
+    }
+
+    pub fn with_options(mut writer: W, size: usize, options: &Lz77Options) -> io::Result<Lz77Encoder<W>> {
+        check_options(options)?;
+        if options.level == Lz77Level::Optimal {
+            return Err(Error::InvalidArgument("optimal parsing needs the whole input").into());
+        }
+
+        let header = BiosHeader { kind: BiosCompressionType::Lz77, parameter: 0, size };
+        let header = header.to_bytes(&options.compress_options)?;
+        writer.write_all(&header)?;
+
+        Ok(Lz77Encoder {
+            writer,
+            options: *options,
+            size,
+            written: 0,
+            buffer: Vec::new(),
+            parsed: 0,
+            blocks: Vec::new(),
+            position: 0,
+            output_length: header.len(),
+        })
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.written != self.size {
+            return Err(Error::SizeMismatch { expected: self.size, actual: self.written }.into());
+        }
+
+        self.encode(true)?;
+        if self.options.compress_options.alignment_padding {
+            write_padding(&mut self.writer, self.output_length)?;
+        }
+        Ok(self.writer)
+    }
+
+    fn encode(&mut self, finish: bool) -> io::Result<()> {
+        let end = if finish { self.buffer.len() } else { self.buffer.len().saturating_sub(LZ77_LOOKAHEAD) };
+        if self.parsed < end {
+            self.parsed = parse_range(&self.buffer, &self.options, &mut self.blocks, self.parsed, end);
+        }
+
+        // A partial group can only be written once no more blocks follow
+        let length = if finish { self.blocks.len() } else { self.blocks.len() / 8 * 8 };
+        let mut output: Vec<u8> = Vec::new();
+        for chunk in self.blocks[..length].chunks(8) {
+            write_block_group(&mut output, chunk, &mut self.position)?;
+        }
+        self.blocks.drain(..length);
+        self.writer.write_all(&output)?;
+        self.output_length += output.len();
+
+        let discarded = self.parsed.saturating_sub(WINDOW_SIZE);
+        self.buffer.drain(..discarded);
+        self.parsed -= discarded;
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Lz77Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() > self.size {
+            return Err(Error::SizeLimit { size: self.written + buf.len(), limit: self.size }.into());
+        }
+
+        self.buffer.extend_from_slice(buf);
+        self.written += buf.len();
+
+        if self.buffer.len() - self.parsed >= CHUNK_SIZE + LZ77_LOOKAHEAD {
+            self.encode(false)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub struct RleEncoder<W: Write> {
+    writer: W,
+    size: usize,
+    written: usize,
+    buffer: Vec<u8>,
+    output_length: usize,
+    alignment_padding: bool,
+}
+
+impl<W: Write> RleEncoder<W> {
+    pub fn new(writer: W, size: usize) -> io::Result<RleEncoder<W>> {
+        RleEncoder::with_options(writer, size, &CompressOptions::default())
+    }
+
+    pub fn with_options(mut writer: W, size: usize, options: &CompressOptions) -> io::Result<RleEncoder<W>> {
+        let header = BiosHeader { kind: BiosCompressionType::Rle, parameter: 0, size }.to_bytes(options)?;
+        writer.write_all(&header)?;
+
+        Ok(RleEncoder {
+            writer,
+            size,
+            written: 0,
+            buffer: Vec::new(),
+            output_length: header.len(),
+            alignment_padding: options.alignment_padding,
+        })
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.written != self.size {
+            return Err(Error::SizeMismatch { expected: self.size, actual: self.written }.into());
+        }
+
+        self.encode(true)?;
+        if self.alignment_padding {
+            write_padding(&mut self.writer, self.output_length)?;
+        }
+        Ok(self.writer)
+    }
+
+    fn encode(&mut self, finish: bool) -> io::Result<()> {
+        let end = if finish { self.buffer.len() } else { self.buffer.len().saturating_sub(RLE_LOOKAHEAD) };
+
+        let mut output: Vec<u8> = Vec::new();
+        let consumed = write_rle_blocks(&self.buffer, end, &mut output);
+        self.writer.write_all(&output)?;
+        self.output_length += output.len();
+        self.buffer.drain(..consumed);
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for RleEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() > self.size {
+            return Err(Error::SizeLimit { size: self.written + buf.len(), limit: self.size }.into());
+        }
+
+        self.buffer.extend_from_slice(buf);
+        self.written += buf.len();
+
+        if self.buffer.len() >= CHUNK_SIZE + RLE_LOOKAHEAD {
+            self.encode(false)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn write_padding<W: Write>(writer: &mut W, output_length: usize) -> io::Result<()> {
+    let padding = [0; 3];
+    writer.write_all(&padding[..((output_length + 3) & !3) - output_length])
+}
@@ -0,0 +1,193 @@
+use bios::{compress_best_with_options, compress_chain_with_options, decompress_chain_with_options, decompress_chain_exact_with_options};
+use bios::{compress_best, compress_chain, compress_rle_optimal, decompress, decompress_chain, decompress_chain_exact, BiosMethod};
+use error::Error;
+use options::{CompressOptions, DecompressOptions};
+
+#[test]
+fn test_compress_best_1() {
+    let input: Vec<u8> = vec![0x13; 4096];
+
+    let (output, methods) = compress_best(&input, false, false).unwrap();
+    assert_eq!(methods, vec![BiosMethod::Rle]);
+    assert_eq!(output, compress_rle_optimal(&input).unwrap());
+}
+
+#[test]
+fn test_compress_best_2() {
+    let input: Vec<u8> = Vec::new();
+
+    let (output, methods) = compress_best(&input, false, true).unwrap();
+    assert_eq!(methods.len(), 1);
+    assert_eq!(decompress(&output).unwrap().0, input);
+}
+
+#[test]
+fn test_compress_best_3() {
+    let input: Vec<u8> = (0..1024u16).flat_map(|i| vec![(i * 3) as u8, ((i * 3) >> 8) as u8]).collect();
+
+    let (output, methods) = compress_best(&input, false, true).unwrap();
+    assert_eq!(methods, vec![BiosMethod::DiffFilter16, BiosMethod::Lz77]);
+
+    let (filtered, _) = decompress(&output).unwrap();
+    let (unfiltered, _) = decompress(&filtered).unwrap();
+    assert_eq!(unfiltered, input);
+}
+
+#[test]
+fn test_compress_best_4() {
+    let input: Vec<u8> = (0..1024u16).flat_map(|i| vec![(i * 3) as u8, ((i * 3) >> 8) as u8]).collect();
+
+    let (output, methods) = compress_best(&input, false, false).unwrap();
+    assert_eq!(methods.len(), 1);
+    assert_eq!(decompress(&output).unwrap().0, input);
+}
+
+#[test]
+fn test_compress_chain_1() {
+    let input: Vec<u8> = vec![
+        0x10, 0x00, 0x11, 0x00,
+        0x12, 0x00, 0x13, 0x00,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x30, 0x0C, 0x00, 0x00,
+        0x0B,
+        0x82, 0x08, 0x00, 0x00,
+        0x10, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x01, 0x00,
+    ];
+
+    let output = compress_chain(&input, &[BiosMethod::DiffFilter16, BiosMethod::Rle], false).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_compress_chain_2() {
+    let input: Vec<u8> = vec![0x13; 16];
+
+    let output = compress_chain(&input, &[], false).unwrap();
+    assert_eq!(output, input);
+}
+
+#[test]
+fn test_compress_chain_3() {
+    // Greedy RLE would need a third byte for the leftover literal
+    let input: Vec<u8> = vec![0x42; 132];
+    let expected_output: Vec<u8> = vec![
+        0x30, 0x84, 0x00, 0x00,
+        0xFE, 0x42,
+        0x80, 0x42,
+    ];
+
+    let output = compress_chain(&input, &[BiosMethod::Rle], false).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_compress_and_decompress_chain_1() {
+    let input: Vec<u8> = (0..1024u16).flat_map(|i| vec![(i * 3) as u8, ((i * 3) >> 8) as u8]).collect();
+
+    let chains: Vec<Vec<BiosMethod>> = vec![
+        vec![BiosMethod::Lz77],
+        vec![BiosMethod::DiffFilter16, BiosMethod::Lz77],
+        vec![BiosMethod::DiffFilter8, BiosMethod::Huffman4],
+        vec![BiosMethod::DiffFilter16, BiosMethod::Rle, BiosMethod::Huffman8],
+    ];
+
+    for chain in chains {
+        let immediate = compress_chain(&input, &chain, false).unwrap();
+        let (output, methods) = decompress_chain(&immediate).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(methods, chain);
+    }
+}
+
+#[test]
+fn test_decompress_chain_1() {
+    // The decoded data starts with a valid header but has trailing data
+    let input: Vec<u8> = vec![
+        0x81, 0x08, 0x00, 0x00,
+        0x10, 0xF0, 0x00, 0x00,
+        0x01, 0x01, 0x01, 0x01,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x00, 0x00, 0x00,
+        0x01, 0x02, 0x03, 0x04,
+    ];
+
+    let (output, methods) = decompress_chain(&input).unwrap();
+    assert_eq!(output, expected_output);
+    assert_eq!(methods, vec![BiosMethod::DiffFilter8]);
+}
+
+#[test]
+fn test_compress_and_decompress_chain_2() {
+    // Plain data that happens to start with a valid stream
+    let inputs: Vec<Vec<u8>> = vec![
+        vec![0x30, 0x01, 0x00, 0x00, 0x00, 0x41, 0x42, 0x43],
+        vec![0x10, 0x00, 0x00, 0x00],
+    ];
+
+    for input in inputs {
+        let immediate = compress_chain(&input, &[BiosMethod::Lz77], false).unwrap();
+        let (output, methods) = decompress_chain(&immediate).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(methods, vec![BiosMethod::Lz77]);
+    }
+}
+
+#[test]
+fn test_compress_and_decompress_chain_exact_1() {
+    let inputs: Vec<Vec<u8>> = vec![
+        vec![0x30, 0x01, 0x00, 0x00, 0x00, 0x41, 0x42, 0x43],
+        vec![0x10, 0x00, 0x00, 0x00],
+        vec![0x81, 0x02, 0x00, 0x00, 0x05, 0x06],
+    ];
+
+    let chains: Vec<Vec<BiosMethod>> = vec![
+        vec![],
+        vec![BiosMethod::Lz77],
+        vec![BiosMethod::Rle, BiosMethod::Lz77],
+        vec![BiosMethod::DiffFilter8, BiosMethod::Huffman4],
+    ];
+
+    for input in &inputs {
+        for chain in &chains {
+            let immediate = compress_chain(input, chain, false).unwrap();
+            assert_eq!(decompress_chain_exact(&immediate, chain).unwrap(), *input);
+        }
+    }
+}
+
+#[test]
+fn test_decompress_chain_exact_1() {
+    // The inner RLE stream is followed by trailing data
+    let input: Vec<u8> = vec![0x30, 0x01, 0x00, 0x00, 0x00, 0x41, 0x42, 0x43];
+    let immediate = compress_chain(&input, &[BiosMethod::DiffFilter8], false).unwrap();
+
+    assert_eq!(decompress_chain_exact(&immediate, &[BiosMethod::DiffFilter8]).unwrap(), input);
+    assert_eq!(decompress_chain_exact(&immediate, &[BiosMethod::Rle, BiosMethod::DiffFilter8]),
+               Err(Error::InvalidPadding { input_offset: 6 }));
+    assert_eq!(decompress_chain_exact(&immediate, &[BiosMethod::Lz77]),
+               Err(Error::HeaderMismatch { input_offset: 0 }));
+}
+
+#[test]
+fn test_compress_and_decompress_chain_options_1() {
+    let input: Vec<u8> = (0..1023u16).flat_map(|i| vec![(i * 3) as u8, ((i * 3) >> 8) as u8]).chain(Some(0x42)).collect();
+    let chain = [BiosMethod::DiffFilter8, BiosMethod::Rle, BiosMethod::Lz77];
+
+    let options = CompressOptions { extended_header: true, alignment_padding: true };
+    let decompress_options = DecompressOptions { extended_header: true, alignment_padding: true, ..DecompressOptions::default() };
+
+    let immediate = compress_chain_with_options(&input, &chain, false, &options).unwrap();
+    assert_eq!(immediate.len() % 4, 0);
+    assert_eq!(decompress_chain_exact_with_options(&immediate, &chain, &decompress_options).unwrap(), input);
+
+    let (output, methods) = decompress_chain_with_options(&immediate, &decompress_options).unwrap();
+    assert_eq!(output, input);
+    assert_eq!(methods, chain);
+
+    let (immediate, methods) = compress_best_with_options(&input, false, true, &options).unwrap();
+    assert_eq!(immediate.len() % 4, 0);
+    assert_eq!(decompress_chain_exact_with_options(&immediate, &methods, &decompress_options).unwrap(), input);
+}
@@ -0,0 +1,299 @@
+use bios::{decompress, decompress_chain_with_options, decompress_with_length, decompress_with_options};
+use bios::{BiosCompressionType, BiosHeader};
+use bios::{compress_huffman_with_options, compress_lz77_with_options, compress_rle_with_options, compress_rle_optimal_with_options};
+use bios::{filter_diff8_with_options, filter_diff16_with_options, HuffmanBitLength, Lz77Options};
+use bios::{decompress_huffman_into_with_options, decompress_lz77_into_with_options, decompress_rle_into_with_options};
+use bios::{unfilter_diff8_into_with_options, unfilter_diff16_into_with_options};
+use error::Error;
+use options::{CompressOptions, DecompressOptions};
+
+#[test]
+fn test_decompress_lz77() {
+    let input: Vec<u8> = vec![
+        0x10, 0x08, 0x00, 0x00,
+        0x08,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x03,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04,
+        0x01, 0x02, 0x03, 0x04,
+    ];
+
+    let (output, header) = decompress(&input).unwrap();
+    assert_eq!(output, expected_output);
+    assert_eq!(header, BiosHeader { kind: BiosCompressionType::Lz77, parameter: 0, size: 8 });
+}
+
+#[test]
+fn test_decompress_huffman() {
+    let input: Vec<u8> = vec![
+        0x24, 0x02, 0x00, 0x00,
+        0x01, 0xC0, 0x01, 0x02,
+        0x00, 0x00, 0x00, 0x50,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x21, 0x21,
+    ];
+
+    let (output, header) = decompress(&input).unwrap();
+    assert_eq!(output, expected_output);
+    assert_eq!(header, BiosHeader { kind: BiosCompressionType::Huffman, parameter: 4, size: 2 });
+}
+
+#[test]
+fn test_decompress_rle() {
+    let input: Vec<u8> = vec![
+        0x30, 0x08, 0x00, 0x00,
+        0x03, 0x01, 0x02, 0x03, 0x04,
+        0x81, 0x05,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04,
+        0x05, 0x05, 0x05, 0x05,
+    ];
+
+    let (output, header) = decompress(&input).unwrap();
+    assert_eq!(output, expected_output);
+    assert_eq!(header, BiosHeader { kind: BiosCompressionType::Rle, parameter: 0, size: 8 });
+}
+
+#[test]
+fn test_decompress_diff8() {
+    let input: Vec<u8> = vec![
+        0x81, 0x04, 0x00, 0x00,
+        0x10, 0x10, 0x10, 0x10,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x20, 0x30, 0x40,
+    ];
+
+    let (output, header) = decompress(&input).unwrap();
+    assert_eq!(output, expected_output);
+    assert_eq!(header, BiosHeader { kind: BiosCompressionType::DiffFilter, parameter: 1, size: 4 });
+}
+
+#[test]
+fn test_decompress_diff16() {
+    let input: Vec<u8> = vec![
+        0x82, 0x04, 0x00, 0x00,
+        0x10, 0x10, 0x01, 0x00,
+        0xAA, 0xBB,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x10, 0x11, 0x10,
+    ];
+
+    let (output, length, header) = decompress_with_length(&input).unwrap();
+    assert_eq!(output, expected_output);
+    assert_eq!(length, 8);
+    assert_eq!(header, BiosHeader { kind: BiosCompressionType::DiffFilter, parameter: 2, size: 4 });
+}
+
+#[test]
+fn test_decompress_unknown() {
+    assert!(decompress(&[0x00, 0x00, 0x00, 0x00]).is_err());
+    assert!(decompress(&[0x83, 0x00, 0x00, 0x00]).is_err());
+    assert!(decompress(&[]).is_err());
+}
+
+#[test]
+fn test_decompress_with_options_1() {
+    let input: Vec<u8> = vec![
+        0x30, 0xFF, 0xFF, 0xFF,
+        0xFF, 0x00,
+    ];
+
+    let options = DecompressOptions { max_output_size: Some(0x10000), ..DecompressOptions::default() };
+    assert_eq!(decompress_with_options(&input, &options), Err(Error::SizeLimit { size: 0xFFFFFF, limit: 0x10000 }));
+}
+
+#[test]
+fn test_decompress_with_options_2() {
+    let input: Vec<u8> = vec![
+        0x81, 0x04, 0x00, 0x00,
+        0x10, 0x10, 0x10, 0x10,
+    ];
+
+    let options = DecompressOptions { expected_size: Some(4), ..DecompressOptions::default() };
+    assert_eq!(decompress_with_options(&input, &options).unwrap().0, vec![0x10, 0x20, 0x30, 0x40]);
+
+    let options = DecompressOptions { expected_size: Some(8), ..DecompressOptions::default() };
+    assert_eq!(decompress_with_options(&input, &options), Err(Error::SizeMismatch { expected: 8, actual: 4 }));
+}
+
+#[test]
+fn test_decompress_size_bound_1() {
+    // Six bytes cannot hold 16 MiB of output in any format
+    let inputs: Vec<(Vec<u8>, usize)> = vec![
+        (vec![0x10, 0xFF, 0xFF, 0xFF, 0xFF, 0x00], 6 * 9),
+        (vec![0x28, 0xFF, 0xFF, 0xFF, 0xFF, 0x00], 6 * 8),
+        (vec![0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0x00], 6 * 65),
+        (vec![0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0x00], 6),
+    ];
+
+    for (input, limit) in inputs {
+        assert_eq!(decompress(&input), Err(Error::SizeLimit { size: 0xFFFFFF, limit }));
+    }
+
+    // The longest possible RLE run is still accepted
+    let input: Vec<u8> = vec![
+        0x30, 0x82, 0x00, 0x00,
+        0xFF, 0x13,
+    ];
+    assert_eq!(decompress(&input).unwrap().0, vec![0x13; 130]);
+}
+
+#[test]
+fn test_decompress_size_bound_2() {
+    // Extended sizes would not even fit the address space of 32-bit targets
+    let input: Vec<u8> = vec![
+        0x10, 0x00, 0x00, 0x00,
+        0xFF, 0xFF, 0xFF, 0xFF,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+    assert_eq!(decompress_with_options(&input, &options), Err(Error::SizeLimit { size: 0xFFFFFFFF, limit: 12 * 9 }));
+    assert_eq!(decompress_chain_with_options(&input, &options), Err(Error::SizeLimit { size: 0xFFFFFFFF, limit: 12 * 9 }));
+}
+
+#[test]
+fn test_decompress_chain_with_options_1() {
+    // An RLE stream wrapping the 8 byte Diff8 stream above
+    let input: Vec<u8> = vec![
+        0x30, 0x08, 0x00, 0x00,
+        0x07, 0x81, 0x04, 0x00, 0x00, 0x10, 0x10, 0x10, 0x10,
+    ];
+
+    let options = DecompressOptions { max_output_size: Some(8), expected_size: Some(4), ..DecompressOptions::default() };
+    assert_eq!(decompress_chain_with_options(&input, &options).unwrap().0, vec![0x10, 0x20, 0x30, 0x40]);
+
+    let options = DecompressOptions { max_output_size: Some(4), ..DecompressOptions::default() };
+    assert_eq!(decompress_chain_with_options(&input, &options), Err(Error::SizeLimit { size: 8, limit: 4 }));
+}
+
+#[test]
+fn test_compress_and_decompress_padding_1() {
+    let input: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04, 0x01, 0x02, 0x03, 0x04,
+        0x05, 0x05, 0x05, 0x05, 0x05, 0x06,
+    ];
+    let options = CompressOptions { alignment_padding: true, ..CompressOptions::default() };
+    let decompress_options = DecompressOptions { alignment_padding: true, ..DecompressOptions::default() };
+
+    let outputs = vec![
+        compress_lz77_with_options(&input, &Lz77Options { compress_options: CompressOptions { alignment_padding: true, ..CompressOptions::default() }, ..Lz77Options::default() }).unwrap(),
+        compress_huffman_with_options(&input, HuffmanBitLength::Bits4, &options).unwrap(),
+        compress_rle_with_options(&input, &options).unwrap(),
+        compress_rle_optimal_with_options(&input, &options).unwrap(),
+        filter_diff8_with_options(&input, &options).unwrap(),
+        filter_diff16_with_options(&input, &options).unwrap(),
+    ];
+
+    for immediate in outputs {
+        assert_eq!(immediate.len() % 4, 0);
+
+        let (output, length, _) = decompress_with_options(&immediate, &decompress_options).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(length, immediate.len());
+    }
+}
+
+#[test]
+fn test_decompress_padding_1() {
+    let input: Vec<u8> = vec![
+        0x30, 0x08, 0x00, 0x00,
+        0x03, 0x01, 0x02, 0x03, 0x04,
+        0x81, 0x05,
+        0x00,
+    ];
+    let options = DecompressOptions { alignment_padding: true, ..DecompressOptions::default() };
+
+    assert_eq!(decompress_with_options(&input, &options).unwrap().1, 12);
+    assert_eq!(decompress_with_options(&input[..11], &options),
+        Err(Error::TruncatedInput { input_offset: 11, output_position: 8 }));
+    assert_eq!(decompress_with_length(&input[..11]).unwrap().1, 11);
+
+    let mut input = input;
+    input[11] = 0xFF;
+    assert_eq!(decompress_with_options(&input, &options), Err(Error::InvalidPadding { input_offset: 11 }));
+    assert_eq!(decompress_with_length(&input).unwrap().1, 12);
+}
+
+#[test]
+fn test_decompress_into_padding_1() {
+    let input: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04, 0x01, 0x02, 0x03, 0x04,
+        0x05, 0x05, 0x05, 0x05, 0x05, 0x06,
+    ];
+    let options = CompressOptions { alignment_padding: true, ..CompressOptions::default() };
+    let decompress_options = DecompressOptions { alignment_padding: true, ..DecompressOptions::default() };
+
+    let mut output = [0; 14];
+
+    let immediate = compress_lz77_with_options(&input, &Lz77Options { compress_options: options, ..Lz77Options::default() }).unwrap();
+    assert_eq!(decompress_lz77_into_with_options(&immediate, &mut output, &decompress_options), Ok(14));
+    assert_eq!(output[..], input[..]);
+
+    let immediate = compress_huffman_with_options(&input, HuffmanBitLength::Bits8, &options).unwrap();
+    assert_eq!(decompress_huffman_into_with_options(&immediate, &mut output, &decompress_options), Ok(14));
+    assert_eq!(output[..], input[..]);
+
+    let immediate = compress_rle_with_options(&input, &options).unwrap();
+    assert_eq!(decompress_rle_into_with_options(&immediate, &mut output, &decompress_options), Ok(14));
+    assert_eq!(output[..], input[..]);
+
+    let immediate = filter_diff8_with_options(&input, &options).unwrap();
+    assert_eq!(unfilter_diff8_into_with_options(&immediate, &mut output, &decompress_options), Ok(14));
+    assert_eq!(output[..], input[..]);
+
+    let immediate = filter_diff16_with_options(&input, &options).unwrap();
+    assert_eq!(unfilter_diff16_into_with_options(&immediate, &mut output, &decompress_options), Ok(14));
+    assert_eq!(output[..], input[..]);
+
+    // The Diff16 stream ends two bytes short of the next 32-bit boundary
+    let mut immediate = immediate;
+    immediate[18] = 0xFF;
+    assert_eq!(unfilter_diff16_into_with_options(&immediate, &mut output, &decompress_options),
+        Err(Error::InvalidPadding { input_offset: 18 }));
+}
+
+#[test]
+fn test_decompress_into_options_1() {
+    let input: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04, 0x01, 0x02, 0x03, 0x04,
+        0x05, 0x05, 0x05, 0x05, 0x05, 0x06,
+    ];
+    let options = CompressOptions::default();
+
+    let streams = [
+        compress_lz77_with_options(&input, &Lz77Options::default()).unwrap(),
+        compress_huffman_with_options(&input, HuffmanBitLength::Bits4, &options).unwrap(),
+        compress_rle_with_options(&input, &options).unwrap(),
+        filter_diff8_with_options(&input, &options).unwrap(),
+        filter_diff16_with_options(&input, &options).unwrap(),
+    ];
+
+    // The output buffer is large enough, so only the options can reject the data
+    let mut output = [0; 32];
+    let limit_options = DecompressOptions { max_output_size: Some(4), ..DecompressOptions::default() };
+    let expected_options = DecompressOptions { expected_size: Some(3), ..DecompressOptions::default() };
+    let limit_error = Err(Error::SizeLimit { size: 14, limit: 4 });
+    let expected_error = Err(Error::SizeMismatch { expected: 3, actual: 14 });
+
+    assert_eq!(decompress_lz77_into_with_options(&streams[0], &mut output, &limit_options), limit_error);
+    assert_eq!(decompress_lz77_into_with_options(&streams[0], &mut output, &expected_options), expected_error);
+    assert_eq!(decompress_huffman_into_with_options(&streams[1], &mut output, &limit_options), limit_error);
+    assert_eq!(decompress_huffman_into_with_options(&streams[1], &mut output, &expected_options), expected_error);
+    assert_eq!(decompress_rle_into_with_options(&streams[2], &mut output, &limit_options), limit_error);
+    assert_eq!(decompress_rle_into_with_options(&streams[2], &mut output, &expected_options), expected_error);
+    assert_eq!(unfilter_diff8_into_with_options(&streams[3], &mut output, &limit_options), limit_error);
+    assert_eq!(unfilter_diff8_into_with_options(&streams[3], &mut output, &expected_options), expected_error);
+    assert_eq!(unfilter_diff16_into_with_options(&streams[4], &mut output, &limit_options), limit_error);
+    assert_eq!(unfilter_diff16_into_with_options(&streams[4], &mut output, &expected_options), expected_error);
+
+    let exact_options = DecompressOptions { max_output_size: Some(14), expected_size: Some(14), ..DecompressOptions::default() };
+    assert_eq!(decompress_lz77_into_with_options(&streams[0], &mut output, &exact_options), Ok(14));
+    assert_eq!(output[..14], input[..]);
+}
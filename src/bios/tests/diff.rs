@@ -1,5 +1,8 @@
-use bios::{filter_diff8, unfilter_diff8};
-use bios::{filter_diff16, unfilter_diff16};
+use bios::{filter_diff8, unfilter_diff8, unfilter_diff8_into, unfilter_diff8_with_length};
+use bios::{filter_diff16, unfilter_diff16, unfilter_diff16_into, unfilter_diff16_with_length};
+use bios::{filter_diff16_with_options, unfilter_diff16_with_options};
+use error::Error;
+use options::{CompressOptions, DecompressOptions};
 
 #[test]
 fn test_unfilter_1() {
@@ -168,3 +171,110 @@ fn test_filter_unfilter_diff16_3() {
     let output = unfilter_diff16(&immediate).unwrap();
     assert_eq!(input, output);
 }
+
+#[test]
+fn test_unfilter_with_length_1() {
+    let input: Vec<u8> = vec![
+        0x81, 0x03, 0x00, 0x00,
+        0x10, 0x10, 0x10,
+        0x00,
+        0xAA, 0xBB,
+    ];
+
+    let (output, length) = unfilter_diff8_with_length(&input).unwrap();
+    assert_eq!(output.len(), 3);
+    assert_eq!(length, 8);
+}
+
+#[test]
+fn test_unfilter_with_length_2() {
+    let input: Vec<u8> = vec![
+        0x82, 0x02, 0x00, 0x00,
+        0x10, 0x10,
+    ];
+
+    let (output, length) = unfilter_diff16_with_length(&input).unwrap();
+    assert_eq!(output.len(), 2);
+    assert_eq!(length, 6);
+}
+
+#[test]
+fn test_unfilter_into_1() {
+    let input: Vec<u8> = vec![
+        0x81, 0x04, 0x00, 0x00,
+        0x10, 0x10, 0x10, 0x10,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x20, 0x30, 0x40,
+    ];
+
+    let mut output: Vec<u8> = vec![0; 4];
+    assert_eq!(unfilter_diff8_into(&input, &mut output).unwrap(), 4);
+    assert_eq!(output, expected_output);
+
+    let mut output: Vec<u8> = vec![0; 3];
+    assert!(unfilter_diff8_into(&input, &mut output).is_err());
+}
+
+#[test]
+fn test_unfilter_into_2() {
+    let input: Vec<u8> = vec![
+        0x82, 0x04, 0x00, 0x00,
+        0x10, 0x10, 0x01, 0x00,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x10, 0x11, 0x10,
+    ];
+
+    let mut output: Vec<u8> = vec![0; 4];
+    assert_eq!(unfilter_diff16_into(&input, &mut output).unwrap(), 4);
+    assert_eq!(output, expected_output);
+
+    let mut output: Vec<u8> = vec![0; 3];
+    assert!(unfilter_diff16_into(&input, &mut output).is_err());
+}
+
+#[test]
+fn test_unfilter_errors_1() {
+    let input: Vec<u8> = vec![
+        0x82, 0x03, 0x00, 0x00,
+        0x10, 0x10, 0x10,
+    ];
+
+    assert_eq!(unfilter_diff16(&input), Err(Error::InvalidSize { size: 3 }));
+    assert_eq!(unfilter_diff8(&input), Err(Error::HeaderMismatch { input_offset: 0 }));
+}
+
+#[test]
+fn test_unfilter_errors_2() {
+    // The size is odd even though the data would cover it
+    let input: Vec<u8> = vec![
+        0x82, 0x05, 0x00, 0x00,
+        0x10, 0x10, 0x10, 0x10,
+        0x10, 0x10,
+    ];
+
+    let mut output = [0; 6];
+    assert_eq!(unfilter_diff16_into(&input, &mut output), Err(Error::InvalidSize { size: 5 }));
+    assert_eq!(unfilter_diff16_with_options(&input, &DecompressOptions::default()), Err(Error::InvalidSize { size: 5 }));
+}
+
+#[test]
+fn test_filter_errors_1() {
+    let input: Vec<u8> = vec![
+        0x10, 0x10, 0x10,
+    ];
+
+    assert!(matches!(filter_diff16(&input), Err(Error::InvalidArgument(_))));
+}
+
+#[test]
+fn test_filter_and_unfilter_extended_1() {
+    let input: Vec<u8> = vec![0x10, 0x10, 0x11, 0x10];
+
+    let immediate = filter_diff16_with_options(&input, &CompressOptions { extended_header: true, ..CompressOptions::default() }).unwrap();
+    assert_eq!(immediate, filter_diff16(&input).unwrap());
+
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+    assert_eq!(unfilter_diff16_with_options(&immediate, &options).unwrap(), (input, 8));
+}
@@ -0,0 +1,68 @@
+use bios::{peek_decompressed_size, BiosCompressionType, BiosHeader};
+use error::Error;
+use options::{CompressOptions, DecompressOptions};
+
+#[test]
+fn test_parse_1() {
+    let input: Vec<u8> = vec![
+        0x28, 0x04, 0x03, 0x02,
+        0x01, 0xC0, 0x41, 0x42,
+    ];
+
+    let header = BiosHeader::parse(&input).unwrap();
+    assert_eq!(header, BiosHeader { kind: BiosCompressionType::Huffman, parameter: 8, size: 0x020304 });
+    assert_eq!(peek_decompressed_size(&input).unwrap(), 0x020304);
+}
+
+#[test]
+fn test_parse_2() {
+    let input: Vec<u8> = vec![
+        0x10, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01,
+    ];
+
+    assert_eq!(BiosHeader::parse(&input).unwrap().size, 0);
+
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+    assert_eq!(BiosHeader::parse_with_options(&input, &options).unwrap().size, 0x1000000);
+}
+
+#[test]
+fn test_parse_errors_1() {
+    assert_eq!(BiosHeader::parse(&[0x11, 0x04, 0x00, 0x00]), Err(Error::HeaderMismatch { input_offset: 0 }));
+    assert_eq!(BiosHeader::parse(&[0x26, 0x04, 0x00, 0x00]), Err(Error::HeaderMismatch { input_offset: 0 }));
+    assert_eq!(BiosHeader::parse(&[0x31, 0x04, 0x00, 0x00]), Err(Error::HeaderMismatch { input_offset: 0 }));
+    assert_eq!(BiosHeader::parse(&[0x83, 0x04, 0x00, 0x00]), Err(Error::HeaderMismatch { input_offset: 0 }));
+    assert_eq!(BiosHeader::parse(&[0x40, 0x04, 0x00, 0x00]), Err(Error::HeaderMismatch { input_offset: 0 }));
+    assert_eq!(peek_decompressed_size(&[0x30, 0x04, 0x00]), Err(Error::TruncatedInput { input_offset: 1, output_position: 0 }));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_write_1() {
+    let header = BiosHeader { kind: BiosCompressionType::DiffFilter, parameter: 2, size: 0x1234 };
+
+    let mut output: Vec<u8> = Vec::new();
+    header.write(&mut output).unwrap();
+    assert_eq!(output, vec![0x82, 0x34, 0x12, 0x00]);
+    assert_eq!(BiosHeader::parse(&output).unwrap(), header);
+
+    let header = BiosHeader { kind: BiosCompressionType::Huffman, parameter: 2, size: 0 };
+    assert!(header.write(&mut output).is_err());
+}
+
+#[test]
+fn test_write_2() {
+    let header = BiosHeader { kind: BiosCompressionType::Rle, parameter: 0, size: 0x1000000 };
+
+    assert_eq!(header.to_bytes(&CompressOptions::default()), Err(Error::SizeLimit { size: 0x1000000, limit: 0xFFFFFF }));
+    assert_eq!(header.to_bytes(&CompressOptions { extended_header: true, ..CompressOptions::default() }).unwrap(),
+        vec![0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+}
+
+#[test]
+fn test_write_errors_1() {
+    let header = BiosHeader { kind: BiosCompressionType::Huffman, parameter: 2, size: 0 };
+
+    assert_eq!(header.to_bytes(&CompressOptions::default()), Err(Error::InvalidArgument("invalid header parameter")));
+}
@@ -1,9 +1,239 @@
-use bios::{compress_huffman, decompress_huffman};
+use bios::{compress_huffman, compress_huffman_with_bit_length, HuffmanBitLength};
+use bios::{decompress_huffman, decompress_huffman_into, decompress_huffman_with_length};
+use bios::{compress_huffman_with_options, decompress_huffman_with_options};
+use error::Error;
+use options::{CompressOptions, DecompressOptions};
 
 #[test]
 fn test_decompress_1() {
+    let input: Vec<u8> = vec![
+        0x28, 0x04, 0x00, 0x00,
+        0x01, 0xC0, 0x41, 0x42,
+        0x00, 0x00, 0x00, 0x10,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x41, 0x41, 0x41, 0x42,
+    ];
+
+    let output = decompress_huffman(&input).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_decompress_2() {
+    let input: Vec<u8> = vec![
+        0x28, 0x04, 0x00, 0x00,
+        0x01, 0xC0, 0x41, 0x42,
+    ];
+
+    assert!(decompress_huffman(&input).is_err());
+}
+
+#[test]
+fn test_decompress_3() {
+    let input: Vec<u8> = vec![
+        0x24, 0x02, 0x00, 0x00,
+        0x01, 0xC0, 0x01, 0x02,
+        0x00, 0x00, 0x00, 0x50,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x21, 0x21,
+    ];
+
+    let output = decompress_huffman(&input).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_decompress_4() {
+    let input: Vec<u8> = vec![
+        0x23, 0x02, 0x00, 0x00,
+        0x01, 0xC0, 0x01, 0x02,
+        0x00, 0x00, 0x00, 0x50,
+    ];
+
+    assert!(decompress_huffman(&input).is_err());
 }
 
 #[test]
 fn test_compress_1() {
+    let input: Vec<u8> = vec![
+        0x41, 0x41, 0x41, 0x42,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x28, 0x04, 0x00, 0x00,
+        0x01, 0xC0, 0x42, 0x41,
+        0x00, 0x00, 0x00, 0xE0,
+    ];
+
+    let output = compress_huffman(&input).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_compress_and_decompress_1() {
+    let input: Vec<u8> = Vec::new();
+
+    let immediate = compress_huffman(&input).unwrap();
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_2() {
+    let input: Vec<u8> = vec![0x13; 4096];
+
+    let immediate = compress_huffman(&input).unwrap();
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_3() {
+    let input: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04,
+        0x05, 0x05, 0x05, 0x05,
+        0x01, 0x02, 0x03, 0x04,
+        0x05, 0x05, 0x05, 0x05,
+    ];
+
+    let immediate = compress_huffman(&input).unwrap();
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_4() {
+    let input: Vec<u8> = (0..64).collect();
+
+    let immediate = compress_huffman(&input).unwrap();
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_5() {
+    let input: Vec<u8> = vec![
+        0x01, 0x12, 0x23, 0x34,
+        0x45, 0x55, 0x55, 0x55,
+        0x01, 0x12, 0x23, 0x34,
+        0x45, 0x55, 0x55, 0x55,
+    ];
+
+    let immediate = compress_huffman_with_bit_length(&input, HuffmanBitLength::Bits4).unwrap();
+    assert_eq!(immediate[0], 0x24);
+
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_6() {
+    let input: Vec<u8> = (0..64).collect();
+
+    let immediate = compress_huffman_with_bit_length(&input, HuffmanBitLength::Auto).unwrap();
+    assert_eq!(immediate[0], 0x24);
+
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_7() {
+    let input: Vec<u8> = vec![0x13; 4096];
+
+    let immediate = compress_huffman_with_bit_length(&input, HuffmanBitLength::Auto).unwrap();
+    assert_eq!(immediate[0], 0x28);
+
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_8() {
+    let input: Vec<u8> = (0..=255).collect();
+
+    let immediate = compress_huffman(&input).unwrap();
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_9() {
+    // Skewed symbol frequencies produce a deep and unbalanced tree
+    let mut input: Vec<u8> = Vec::new();
+    for i in 0..=255 {
+        for _ in 0..(1 << (i % 24 / 2)) {
+            input.push(i as u8);
+        }
+    }
+
+    let immediate = compress_huffman(&input).unwrap();
+    let output = decompress_huffman(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_decompress_with_length_1() {
+    let input: Vec<u8> = vec![
+        0x28, 0x04, 0x00, 0x00,
+        0x01, 0xC0, 0x41, 0x42,
+        0x00, 0x00, 0x00, 0x10,
+        0xAA, 0xBB,
+    ];
+
+    let (output, length) = decompress_huffman_with_length(&input).unwrap();
+    assert_eq!(output.len(), 4);
+    assert_eq!(length, 12);
+}
+
+#[test]
+fn test_decompress_into_1() {
+    let input: Vec<u8> = vec![
+        0x24, 0x02, 0x00, 0x00,
+        0x01, 0xC0, 0x01, 0x02,
+        0x00, 0x00, 0x00, 0x50,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x21, 0x21,
+    ];
+
+    let mut output: Vec<u8> = vec![0; 2];
+    assert_eq!(decompress_huffman_into(&input, &mut output).unwrap(), 2);
+    assert_eq!(output, expected_output);
+
+    let mut output: Vec<u8> = vec![0; 1];
+    assert!(decompress_huffman_into(&input, &mut output).is_err());
+}
+
+#[test]
+fn test_decompress_errors_1() {
+    let input: Vec<u8> = vec![
+        0x28, 0x01, 0x00, 0x00,
+        0x00, 0x3F,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    assert_eq!(decompress_huffman(&input), Err(Error::InvalidTree { input_offset: 5, output_position: 0 }));
+}
+
+#[test]
+fn test_decompress_errors_2() {
+    let input: Vec<u8> = vec![
+        0x28, 0x04, 0x00, 0x00,
+        0x01, 0xC0, 0x41, 0x42,
+    ];
+
+    assert_eq!(decompress_huffman(&input), Err(Error::TruncatedInput { input_offset: 8, output_position: 0 }));
+}
+
+#[test]
+fn test_compress_and_decompress_extended_1() {
+    let input: Vec<u8> = Vec::new();
+
+    let immediate = compress_huffman_with_options(&input, HuffmanBitLength::Bits8, &CompressOptions { extended_header: true, ..CompressOptions::default() }).unwrap();
+    assert_eq!(&immediate[..8], &[0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+    assert_eq!(decompress_huffman_with_options(&immediate, &options).unwrap(), (input, immediate.len()));
 }
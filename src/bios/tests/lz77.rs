@@ -1,6 +1,10 @@
-use bios::{compress_lz77, decompress_lz77};
-
-// TODO: Add tests for out of bounds cases
+use bios::{compress_lz77, compress_lz77_optimal, compress_lz77_with_options};
+use bios::{decompress_lz77, decompress_lz77_into, decompress_lz77_with_length};
+use bios::{decompress_lz77_with_options, Lz77Level, Lz77Options, Lz77TieBreak};
+use std::cmp;
+use error::Error;
+use options::{CompressOptions, DecompressOptions};
+use utils::XorShift;
 
 #[test]
 fn test_decompress_1() {
@@ -109,3 +113,498 @@ fn test_compress_vram_safe_2() {
     let output = decompress_lz77(&immediate).unwrap();
     assert_eq!(input, output);
 }
+
+#[test]
+fn test_compress_optimal_1() {
+    let input: Vec<u8> = vec![
+        0x02, 0x02, 0x02, 0x01,
+        0x02, 0x02, 0x02, 0x02,
+        0x01,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x09, 0x00, 0x00,
+        0x04,
+        0x02, 0x02, 0x02, 0x01, 0x02,
+        0x10, 0x04,
+    ];
+
+    let output = compress_lz77_optimal(&input, false).unwrap();
+    assert_eq!(output, expected_output);
+    assert!(output.len() < compress_lz77(&input, false).unwrap().len());
+}
+
+#[test]
+fn test_compress_optimal_2() {
+    let input: Vec<u8> = vec![0xFF; 16];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x10, 0x00, 0x00,
+        0x20, 0xFF, 0xFF, 0xB0, 0x01
+    ];
+
+    let output = compress_lz77_optimal(&input, true).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_compress_and_decompress_optimal_1() {
+    let input: Vec<u8> = Vec::new();
+
+    let immediate = compress_lz77_optimal(&input, false).unwrap();
+    let output = decompress_lz77(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_optimal_2() {
+    let input: Vec<u8> = (0..4096).map(|i| ((i * i) % 7) as u8).collect();
+
+    let immediate = compress_lz77_optimal(&input, false).unwrap();
+    let output = decompress_lz77(&immediate).unwrap();
+    assert_eq!(input, output);
+    assert!(immediate.len() <= compress_lz77(&input, false).unwrap().len());
+}
+
+#[test]
+fn test_compress_and_decompress_optimal_3() {
+    let input: Vec<u8> = vec![0xFF; 4096];
+
+    let immediate = compress_lz77_optimal(&input, true).unwrap();
+    let output = decompress_lz77(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_and_decompress_5() {
+    let mut input: Vec<u8> = (0..8192).map(|i| ((i * 7) % 251) as u8).collect();
+    input.extend_from_slice(&[0x00, 0x07, 0x0E, 0x15]);
+
+    let immediate = compress_lz77(&input, false).unwrap();
+    let output = decompress_lz77(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_options_1() {
+    let input: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x09,
+        0x01, 0x02, 0x03, 0x08,
+        0x01, 0x02, 0x03,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x0B, 0x00, 0x00,
+        0x0A,
+        0x01, 0x02, 0x03, 0x09,
+        0x00, 0x03,
+        0x08,
+        0x00, 0x07,
+    ];
+
+    let options = Lz77Options {
+        tie_break: Lz77TieBreak::Farthest,
+        ..Lz77Options::default()
+    };
+
+    let output = compress_lz77_with_options(&input, &options).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_compress_options_2() {
+    let input: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x09,
+        0x01, 0x02, 0x03, 0x08,
+        0x01, 0x02, 0x03,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x0B, 0x00, 0x00,
+        0x0A,
+        0x01, 0x02, 0x03, 0x09,
+        0x00, 0x03,
+        0x08,
+        0x00, 0x03,
+    ];
+
+    let options = Lz77Options {
+        window_size: 4,
+        tie_break: Lz77TieBreak::Farthest,
+        ..Lz77Options::default()
+    };
+
+    let output = compress_lz77_with_options(&input, &options).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_compress_options_3() {
+    let input: Vec<u8> = vec![0xFF; 16];
+
+    let options = Lz77Options {
+        window_size: 0,
+        ..Lz77Options::default()
+    };
+    assert!(compress_lz77_with_options(&input, &options).is_err());
+
+    let options = Lz77Options {
+        window_size: 4097,
+        ..Lz77Options::default()
+    };
+    assert!(compress_lz77_with_options(&input, &options).is_err());
+
+    let options = Lz77Options {
+        min_offset: 0,
+        ..Lz77Options::default()
+    };
+    assert!(compress_lz77_with_options(&input, &options).is_err());
+}
+
+#[test]
+fn test_compress_options_farthest_1() {
+    let input: Vec<u8> = vec![0x00; 32];
+    let expected_output: Vec<u8> = vec![
+        0x10, 0x20, 0x00, 0x00,
+        0x60,
+        0x00,
+        0xF0, 0x00,
+        0xA0, 0x12,
+    ];
+
+    let options = Lz77Options {
+        tie_break: Lz77TieBreak::Farthest,
+        ..Lz77Options::default()
+    };
+
+    let output = compress_lz77_with_options(&input, &options).unwrap();
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_compress_options_farthest_2() {
+    // The farthest copy of the pattern is not at the edge of the window
+    let pattern: Vec<u8> = (0x10..0x22).collect();
+    let mut input: Vec<u8> = (0x80..0x8A).collect();
+    input.extend_from_slice(&pattern);
+    input.extend_from_slice(&[0xAA; 7]);
+    input.extend_from_slice(&pattern);
+    input.extend_from_slice(&[0xBB; 9]);
+    input.extend_from_slice(&pattern);
+
+    // The last block references all 18 bytes of the pattern at offset 27 or 52
+    for &(tie_break, last_block) in &[(Lz77TieBreak::Nearest, [0xF0, 0x1A]), (Lz77TieBreak::Farthest, [0xF0, 0x33])] {
+        let options = Lz77Options {
+            tie_break,
+            ..Lz77Options::default()
+        };
+
+        let output = compress_lz77_with_options(&input, &options).unwrap();
+        assert_eq!(output[output.len() - 2..], last_block);
+        assert_eq!(decompress_lz77(&output).unwrap(), input);
+    }
+}
+
+#[test]
+fn test_compress_and_decompress_farthest_1() {
+    // Bounding the chain keeps the farthest search fast on long runs
+    let input: Vec<u8> = vec![0x00; 0x100000];
+
+    let options = Lz77Options {
+        tie_break: Lz77TieBreak::Farthest,
+        max_chain_length: 128,
+        ..Lz77Options::default()
+    };
+
+    let immediate = compress_lz77_with_options(&input, &options).unwrap();
+    let output = decompress_lz77(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_compress_options_4() {
+    let input: Vec<u8> = vec![0xFF; 16];
+
+    for &(max_chain_length, nice_length) in &[(0, 18), (1, 2), (1, 19)] {
+        let options = Lz77Options {
+            max_chain_length,
+            nice_length,
+            ..Lz77Options::default()
+        };
+        assert!(compress_lz77_with_options(&input, &options).is_err());
+    }
+}
+
+#[test]
+fn test_compress_and_decompress_low_entropy_1() {
+    // Every position of low-entropy data starts a long hash chain, which the
+    // optional bounds cut short.
+    let mut rng = XorShift::new(0x0005_0005);
+    let inputs = vec![rng.bytes(0x40000, 2), vec![0x00; 0x40000]];
+
+    for input in &inputs {
+        for &(max_chain_length, nice_length) in &[(1, 3), (16, 8), (128, 18)] {
+            let options = Lz77Options {
+                max_chain_length,
+                nice_length,
+                ..Lz77Options::default()
+            };
+
+            let immediate = compress_lz77_with_options(input, &options).unwrap();
+            let output = decompress_lz77(&immediate).unwrap();
+            assert_eq!(input, &output);
+        }
+    }
+}
+
+// The size of a greedy parse trying every offset in the window
+fn brute_force_greedy_size(input: &[u8]) -> usize {
+    let mut size = 4;
+    let mut blocks = 0;
+    let mut index = 0;
+
+    while index < input.len() {
+        let max_length = cmp::min(input.len() - index, 18);
+        let mut best_length = 0;
+
+        for offset in 1..=cmp::min(index, 4096) {
+            let length = (0..max_length).take_while(|&i| input[index + i] == input[index - offset + i]).count();
+            best_length = cmp::max(best_length, length);
+        }
+
+        if blocks % 8 == 0 {
+            size += 1;
+        }
+        blocks += 1;
+
+        if best_length >= 3 {
+            size += 2;
+            index += best_length;
+        } else {
+            size += 1;
+            index += 1;
+        }
+    }
+
+    size
+}
+
+#[test]
+fn test_compress_brute_force_1() {
+    // The default search has to find the same matches as an exhaustive one,
+    // even on low-entropy data with long hash chains.
+    let mut rng = XorShift::new(0x0005_0006);
+    let inputs = vec![rng.bytes(0x2000, 2), rng.bytes(0x2000, 3), vec![0x00; 0x2000]];
+
+    for input in &inputs {
+        let expected_size = brute_force_greedy_size(input);
+        assert_eq!(compress_lz77(input, false).unwrap().len(), expected_size);
+        assert!(compress_lz77_optimal(input, false).unwrap().len() <= expected_size);
+    }
+}
+
+#[test]
+fn test_compress_and_decompress_options_1() {
+    let input: Vec<u8> = (0..4096).map(|i| ((i * i) % 7) as u8).collect();
+
+    for &level in &[Lz77Level::Greedy, Lz77Level::Lazy, Lz77Level::Optimal] {
+        for &tie_break in &[Lz77TieBreak::Nearest, Lz77TieBreak::Farthest] {
+            let options = Lz77Options {
+                level,
+                window_size: 256,
+                min_offset: 4,
+                tie_break,
+                vram_safe: true,
+                ..Lz77Options::default()
+            };
+
+            let immediate = compress_lz77_with_options(&input, &options).unwrap();
+            let output = decompress_lz77(&immediate).unwrap();
+            assert_eq!(input, output);
+        }
+    }
+}
+
+#[test]
+fn test_compress_lazy_1() {
+    let mut rng = XorShift::new(0x0007_0007);
+    let block = rng.bytes(3000, 16);
+    // Tile-like data mixes noise with repeated pieces of a shared block
+    let mut tiles: Vec<u8> = Vec::new();
+    for i in 0..60 {
+        if i % 3 == 0 {
+            tiles.extend(rng.bytes(1000, 4));
+        } else {
+            let start = rng.below(2000);
+            tiles.extend_from_slice(&block[start..start+1000]);
+        }
+    }
+    let inputs = vec![rng.bytes(50000, 2), rng.bytes(50000, 4), rng.bytes(50000, 16), tiles];
+
+    let greedy_options = Lz77Options::default();
+    let lazy_options = Lz77Options {
+        level: Lz77Level::Lazy,
+        ..Lz77Options::default()
+    };
+
+    for input in &inputs {
+        let greedy_output = compress_lz77_with_options(input, &greedy_options).unwrap();
+        let lazy_output = compress_lz77_with_options(input, &lazy_options).unwrap();
+        assert!(lazy_output.len() <= greedy_output.len());
+        assert_eq!(&decompress_lz77(&lazy_output).unwrap(), input);
+    }
+}
+
+#[test]
+fn test_compress_and_decompress_lazy_1() {
+    let input: Vec<u8> = (0..4096).map(|i| ((i * i) % 7) as u8).collect();
+
+    let options = Lz77Options {
+        level: Lz77Level::Lazy,
+        vram_safe: true,
+        ..Lz77Options::default()
+    };
+
+    let immediate = compress_lz77_with_options(&input, &options).unwrap();
+    let output = decompress_lz77(&immediate).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_decompress_with_length_1() {
+    let input: Vec<u8> = vec![
+        0x10, 0x10, 0x00, 0x00,
+        0x0C,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x03,
+        0x50, 0x07,
+        0x00, 0x00, 0x00,
+        0xAA, 0xBB,
+    ];
+
+    let (output, length) = decompress_lz77_with_length(&input).unwrap();
+    assert_eq!(output.len(), 16);
+    assert_eq!(length, 16);
+}
+
+#[test]
+fn test_decompress_with_length_2() {
+    let input: Vec<u8> = vec![
+        0x10, 0x10, 0x00, 0x00,
+        0x0C,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x03,
+        0x50, 0x07,
+    ];
+
+    let (output, length) = decompress_lz77_with_length(&input).unwrap();
+    assert_eq!(output.len(), 16);
+    assert_eq!(length, 13);
+}
+
+#[test]
+fn test_decompress_into_1() {
+    let input: Vec<u8> = vec![
+        0x10, 0x10, 0x00, 0x00,
+        0x0C,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x03,
+        0x50, 0x07,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04,
+        0x01, 0x02, 0x03, 0x04,
+        0x01, 0x02, 0x03, 0x04,
+        0x01, 0x02, 0x03, 0x04,
+        0xFF, 0xFF,
+    ];
+
+    let mut output: Vec<u8> = vec![0xFF; 18];
+    assert_eq!(decompress_lz77_into(&input, &mut output).unwrap(), 16);
+    assert_eq!(output, expected_output);
+}
+
+#[test]
+fn test_decompress_into_2() {
+    let input: Vec<u8> = vec![
+        0x10, 0x10, 0x00, 0x00,
+        0x0C,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x03,
+        0x50, 0x07,
+    ];
+
+    let mut output: Vec<u8> = vec![0; 15];
+    assert!(decompress_lz77_into(&input, &mut output).is_err());
+}
+
+#[test]
+fn test_decompress_errors_1() {
+    let input: Vec<u8> = vec![
+        0x30, 0x04, 0x00, 0x00,
+    ];
+
+    assert_eq!(decompress_lz77(&input), Err(Error::HeaderMismatch { input_offset: 0 }));
+}
+
+#[test]
+fn test_decompress_errors_2() {
+    let input: Vec<u8> = vec![
+        0x10, 0x04, 0x00, 0x00,
+        0x80,
+        0x00, 0x00,
+    ];
+
+    assert_eq!(decompress_lz77(&input), Err(Error::OffsetOutOfBounds { input_offset: 5, output_position: 0 }));
+}
+
+#[test]
+fn test_decompress_errors_3() {
+    let input: Vec<u8> = vec![
+        0x10, 0x02, 0x00, 0x00,
+        0x40,
+        0x01,
+        0x00, 0x00,
+    ];
+
+    assert_eq!(decompress_lz77(&input), Err(Error::LengthOverrun { input_offset: 6, output_position: 1 }));
+}
+
+#[test]
+fn test_decompress_errors_4() {
+    let input: Vec<u8> = vec![
+        0x10, 0x04, 0x00, 0x00,
+        0x00,
+        0x01, 0x02,
+    ];
+
+    assert_eq!(decompress_lz77(&input), Err(Error::TruncatedInput { input_offset: 7, output_position: 2 }));
+}
+
+#[test]
+fn test_compress_and_decompress_extended_1() {
+    let input: Vec<u8> = Vec::new();
+
+    let immediate = compress_lz77_with_options(&input, &Lz77Options { compress_options: CompressOptions { extended_header: true, ..CompressOptions::default() }, ..Lz77Options::default() }).unwrap();
+    assert_eq!(immediate, vec![0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+    assert_eq!(decompress_lz77_with_options(&immediate, &options).unwrap(), (input, 8));
+}
+
+#[test]
+fn test_decompress_extended_1() {
+    let input: Vec<u8> = vec![
+        0x10, 0x00, 0x00, 0x00,
+        0x08, 0x00, 0x00, 0x00,
+        0x08,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x03,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04,
+        0x01, 0x02, 0x03, 0x04,
+    ];
+
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+    assert_eq!(decompress_lz77_with_options(&input, &options).unwrap(), (expected_output, 15));
+
+    let options = DecompressOptions { extended_header: true, max_output_size: Some(4), ..DecompressOptions::default() };
+    assert_eq!(decompress_lz77_with_options(&input, &options), Err(Error::SizeLimit { size: 8, limit: 4 }));
+}
@@ -1,4 +1,10 @@
+mod chain;
+mod decompress;
 mod diff;
+mod header;
 mod huffman;
 mod lz77;
+mod no_panic;
 mod rle;
+#[cfg(feature = "std")]
+mod stream;
@@ -0,0 +1,144 @@
+use bios::{compress_best, compress_chain, compress_huffman, compress_huffman_with_bit_length, compress_lz77_with_options, compress_rle};
+use bios::{decompress, decompress_chain, decompress_with_length};
+use bios::{decompress_huffman, decompress_huffman_into, decompress_huffman_with_length};
+use bios::{decompress_lz77, decompress_lz77_into, decompress_lz77_with_length};
+use bios::{decompress_rle, decompress_rle_into, decompress_rle_with_length};
+use bios::{filter_diff8, filter_diff16, unfilter_diff8_into, unfilter_diff16_into, unfilter_diff_with_length};
+use bios::{BiosMethod, HuffmanBitLength, Lz77Level, Lz77Options, Lz77TieBreak};
+use options::CompressOptions;
+#[cfg(feature = "std")]
+use bios::Lz77Decoder;
+#[cfg(feature = "std")]
+use std::io::Read;
+use utils::XorShift;
+
+fn decompress_all(input: &[u8]) {
+    let mut output: Vec<u8> = vec![0; 64];
+
+    let _ = decompress(input);
+    let _ = decompress_with_length(input);
+    let _ = decompress_chain(input);
+    let _ = decompress_lz77(input);
+    let _ = decompress_lz77_into(input, &mut output);
+    let _ = decompress_lz77_with_length(input);
+    let _ = decompress_rle(input);
+    let _ = decompress_rle_into(input, &mut output);
+    let _ = decompress_rle_with_length(input);
+    let _ = decompress_huffman(input);
+    let _ = decompress_huffman_into(input, &mut output);
+    let _ = decompress_huffman_with_length(input);
+    let _ = unfilter_diff8_into(input, &mut output);
+    let _ = unfilter_diff16_into(input, &mut output);
+    let _ = unfilter_diff_with_length(input);
+
+    #[cfg(feature = "std")]
+    {
+        if let Ok(mut decoder) = Lz77Decoder::new(input) {
+            let _ = decoder.read_to_end(&mut Vec::new());
+        }
+    }
+}
+
+fn valid_streams(input: &[u8]) -> Vec<Vec<u8>> {
+    vec![
+        compress_lz77_with_options(input, &Lz77Options::default()).unwrap(),
+        compress_rle(input).unwrap(),
+        compress_huffman_with_bit_length(input, HuffmanBitLength::Bits4).unwrap(),
+        compress_huffman(input).unwrap(),
+        filter_diff8(input).unwrap(),
+        compress_chain(input, &[BiosMethod::DiffFilter8, BiosMethod::Lz77], false).unwrap(),
+    ]
+}
+
+#[test]
+fn test_random_input() {
+    let mut rng = XorShift::new(0x1234_5678);
+
+    for _ in 0..2000 {
+        let length = rng.below(64);
+        let mut input = rng.bytes(length, 256);
+
+        // Keep the declared size small so that most inputs reach the decoding loop
+        if input.len() >= 4 {
+            input[0] = [0x10, 0x24, 0x28, 0x30, 0x81, 0x82][rng.below(6)];
+            input[3] = 0x00;
+        }
+
+        decompress_all(&input);
+    }
+}
+
+#[test]
+fn test_huge_declared_size() {
+    for &header in &[0x10, 0x24, 0x28, 0x30, 0x81, 0x82] {
+        decompress_all(&[header, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00]);
+        decompress_all(&[header, 0xFF, 0xFF, 0xFF]);
+        decompress_all(&[header]);
+    }
+}
+
+#[test]
+fn test_degenerate_huffman_trees() {
+    // The root points at a branch whose children point back into the table
+    decompress_all(&[0x28, 0x10, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    decompress_all(&[0x28, 0x10, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]);
+    decompress_all(&[0x28, 0x10, 0x00, 0x00, 0xFF, 0x3F, 0x3F, 0x3F, 0x3F, 0x3F, 0x3F, 0x3F]);
+    decompress_all(&[0x24, 0x10, 0x00, 0x00, 0xFF, 0xFF]);
+}
+
+#[test]
+fn test_mutated_streams() {
+    let mut rng = XorShift::new(0x9ABC_DEF0);
+
+    for _ in 0..200 {
+        let length = rng.below(128);
+        let alphabet = 1 + rng.below(255);
+        let input = rng.bytes(length, alphabet);
+
+        for stream in valid_streams(&input) {
+            for length in 0..stream.len() {
+                decompress_all(&stream[..length]);
+            }
+
+            let mut mutated = stream.clone();
+            for _ in 0..4 {
+                let index = rng.below(mutated.len());
+                mutated[index] ^= 1 << rng.below(8);
+                decompress_all(&mutated);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_random_options() {
+    let mut rng = XorShift::new(0x0F0F_0F0F);
+
+    for _ in 0..200 {
+        let length = rng.below(256);
+        let alphabet = 1 + rng.below(255);
+        let input = rng.bytes(length, alphabet);
+
+        let options = Lz77Options {
+            level: [Lz77Level::Greedy, Lz77Level::Lazy, Lz77Level::Optimal][rng.below(3)],
+            window_size: rng.below(5000),
+            min_offset: rng.below(5000),
+            tie_break: [Lz77TieBreak::Nearest, Lz77TieBreak::Farthest][rng.below(2)],
+            max_chain_length: rng.below(300),
+            nice_length: rng.below(20),
+            vram_safe: rng.below(2) == 1,
+            compress_options: CompressOptions {
+                extended_header: rng.below(2) == 1,
+                alignment_padding: rng.below(2) == 1,
+            },
+        };
+
+        if let Ok(output) = compress_lz77_with_options(&input, &options) {
+            assert_eq!(decompress_lz77(&output).unwrap(), input);
+        }
+
+        let _ = filter_diff16(&input);
+        let _ = compress_huffman_with_bit_length(&input, HuffmanBitLength::Auto);
+        let _ = compress_best(&input, options.vram_safe, true);
+    }
+}
@@ -1,4 +1,9 @@
-use bios::{compress_rle, decompress_rle};
+use bios::{compress_rle, decompress_rle, decompress_rle_into, decompress_rle_with_length};
+use bios::{compress_rle_with_options, decompress_rle_with_options};
+use bios::{compress_rle_optimal, compress_rle_optimal_with_options};
+use error::Error;
+use options::{CompressOptions, DecompressOptions};
+use utils::XorShift;
 
 #[test]
 fn test_decompress_1() {
@@ -77,3 +82,155 @@ fn test_compress_and_decompress_3() {
     let output = decompress_rle(&immediate).unwrap();
     assert_eq!(input, output);
 }
+
+#[test]
+fn test_decompress_with_length_1() {
+    let input: Vec<u8> = vec![
+        0x30, 0x08, 0x00, 0x00,
+        0x03, 0x01, 0x02, 0x03, 0x04,
+        0x81, 0x05,
+        0x00,
+        0xAA, 0xBB,
+    ];
+
+    let (output, length) = decompress_rle_with_length(&input).unwrap();
+    assert_eq!(output.len(), 8);
+    assert_eq!(length, 12);
+}
+
+#[test]
+fn test_decompress_into_1() {
+    let input: Vec<u8> = vec![
+        0x30, 0x08, 0x00, 0x00,
+        0x03, 0x01, 0x02, 0x03, 0x04,
+        0x81, 0x05,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04,
+        0x05, 0x05, 0x05, 0x05,
+    ];
+
+    let mut output: Vec<u8> = vec![0; 8];
+    assert_eq!(decompress_rle_into(&input, &mut output).unwrap(), 8);
+    assert_eq!(output, expected_output);
+
+    let mut output: Vec<u8> = vec![0; 7];
+    assert!(decompress_rle_into(&input, &mut output).is_err());
+}
+
+#[test]
+fn test_decompress_errors_1() {
+    let input: Vec<u8> = vec![
+        0x30, 0x08, 0x00, 0x00,
+        0x03, 0x01, 0x02, 0x03, 0x04,
+        0x83, 0x05,
+    ];
+
+    assert_eq!(decompress_rle(&input), Err(Error::LengthOverrun { input_offset: 9, output_position: 4 }));
+}
+
+#[test]
+fn test_decompress_errors_2() {
+    let input: Vec<u8> = vec![
+        0x30, 0x08, 0x00, 0x00,
+        0x03, 0x01, 0x02, 0x03, 0x04,
+        0x81,
+    ];
+
+    assert_eq!(decompress_rle(&input), Err(Error::TruncatedInput { input_offset: 10, output_position: 4 }));
+}
+
+#[test]
+fn test_compress_size_limit_1() {
+    let input: Vec<u8> = vec![0x00; 0x1000000];
+
+    assert_eq!(compress_rle(&input), Err(Error::SizeLimit { size: 0x1000000, limit: 0xFFFFFF }));
+}
+
+#[test]
+fn test_compress_and_decompress_extended_1() {
+    let input: Vec<u8> = vec![0x00; 0x1000000];
+
+    let immediate = compress_rle_with_options(&input, &CompressOptions { extended_header: true, ..CompressOptions::default() }).unwrap();
+    assert_eq!(&immediate[..8], &[0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+    let (output, length) = decompress_rle_with_options(&immediate, &options).unwrap();
+    assert_eq!(output, input);
+    assert_eq!(length, immediate.len());
+}
+
+#[test]
+fn test_compress_and_decompress_extended_2() {
+    let input: Vec<u8> = Vec::new();
+
+    let immediate = compress_rle_with_options(&input, &CompressOptions { extended_header: true, ..CompressOptions::default() }).unwrap();
+    assert_eq!(immediate, vec![0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+    assert_eq!(decompress_rle_with_options(&immediate, &options).unwrap().0, input);
+    assert_eq!(decompress_rle(&immediate).unwrap(), input);
+}
+
+#[test]
+fn test_compress_and_decompress_extended_3() {
+    // Sizes that fit in 24 bits keep the GBA header even in extended mode
+    let input: Vec<u8> = vec![0x13; 4096];
+
+    let immediate = compress_rle_with_options(&input, &CompressOptions { extended_header: true, ..CompressOptions::default() }).unwrap();
+    assert_eq!(immediate, compress_rle(&input).unwrap());
+}
+
+#[test]
+fn test_compress_optimal_1() {
+    // Greedy parsing takes the longest run and leaves two bytes for a literal
+    let input: Vec<u8> = vec![0x42; 132];
+    let expected_output: Vec<u8> = vec![
+        0x30, 0x84, 0x00, 0x00,
+        0xFE, 0x42,
+        0x80, 0x42,
+    ];
+
+    let output = compress_rle_optimal(&input).unwrap();
+    assert_eq!(output, expected_output);
+    assert!(output.len() < compress_rle(&input).unwrap().len());
+}
+
+#[test]
+fn test_compress_optimal_2() {
+    let input: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x30, 0x04, 0x00, 0x00,
+        0x03, 0x01, 0x02, 0x03, 0x04,
+    ];
+
+    assert_eq!(compress_rle_optimal(&input).unwrap(), expected_output);
+    assert_eq!(compress_rle_optimal(&[]).unwrap(), compress_rle(&[]).unwrap());
+}
+
+#[test]
+fn test_compress_and_decompress_optimal_1() {
+    let mut rng = XorShift::new(0x5EED_0023);
+
+    for i in 0..64 {
+        let alphabet = [2, 3, 16, 256][i % 4];
+        let length = rng.below(2000);
+        let input = rng.bytes(length, alphabet);
+
+        let immediate = compress_rle_optimal(&input).unwrap();
+        assert!(immediate.len() <= compress_rle(&input).unwrap().len());
+
+        let output = decompress_rle(&immediate).unwrap();
+        assert_eq!(input, output);
+    }
+}
+
+#[test]
+fn test_compress_optimal_extended_1() {
+    let input: Vec<u8> = Vec::new();
+
+    let immediate = compress_rle_optimal_with_options(&input, &CompressOptions { extended_header: true, ..CompressOptions::default() }).unwrap();
+    assert_eq!(immediate, vec![0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+}
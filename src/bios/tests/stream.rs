@@ -0,0 +1,258 @@
+use std::cmp;
+use std::io::{self, Read, Write};
+use bios::{compress_lz77, compress_lz77_with_options, compress_rle, compress_rle_with_options, decompress_rle};
+use bios::{Lz77Decoder, Lz77Encoder, Lz77Level, Lz77Options, Lz77TieBreak, RleEncoder};
+use error::Error;
+use options::{CompressOptions, DecompressOptions};
+use utils::XorShift;
+
+#[test]
+fn test_lz77_decoder_1() {
+    let input: Vec<u8> = vec![
+        0x10, 0x08, 0x00, 0x00,
+        0x08,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x03,
+        0xAA,
+    ];
+    let expected_output: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04,
+        0x01, 0x02, 0x03, 0x04,
+    ];
+
+    let mut decoder = Lz77Decoder::new(&input[..]).unwrap();
+    assert_eq!(decoder.decompressed_size(), 8);
+
+    let mut output: Vec<u8> = Vec::new();
+    decoder.read_to_end(&mut output).unwrap();
+    assert_eq!(output, expected_output);
+    assert_eq!(decoder.input_position(), 11);
+    assert_eq!(decoder.into_inner(), &[0xAA]);
+}
+
+#[test]
+fn test_lz77_decoder_2() {
+    // References reaching back across the whole window
+    let mut rng = XorShift::new(0x2468_ACE0);
+    let block = rng.bytes(4096, 256);
+    let input: Vec<u8> = (0..16).flat_map(|_| block.clone()).chain(rng.bytes(1000, 4)).collect();
+
+    let immediate = compress_lz77(&input, false).unwrap();
+    let mut decoder = Lz77Decoder::new(&immediate[..]).unwrap();
+
+    // Reading in small chunks stops in the middle of references
+    let mut output: Vec<u8> = Vec::new();
+    let mut buffer = [0; 7];
+    loop {
+        let length = decoder.read(&mut buffer).unwrap();
+        if length == 0 {
+            break;
+        }
+        output.extend_from_slice(&buffer[..length]);
+    }
+
+    assert_eq!(output, input);
+}
+
+#[test]
+fn test_lz77_decoder_extended_1() {
+    let input: Vec<u8> = (0..64).collect();
+
+    let immediate = compress_lz77_with_options(&input, &Lz77Options { compress_options: CompressOptions { extended_header: true, ..CompressOptions::default() }, ..Lz77Options::default() }).unwrap();
+    let options = DecompressOptions { extended_header: true, ..DecompressOptions::default() };
+
+    let mut output: Vec<u8> = Vec::new();
+    Lz77Decoder::with_options(&immediate[..], &options).unwrap().read_to_end(&mut output).unwrap();
+    assert_eq!(output, input);
+}
+
+#[test]
+fn test_lz77_decoder_errors_1() {
+    let input: Vec<u8> = vec![
+        0x10, 0x08, 0x00, 0x00,
+        0x08,
+        0x01, 0x02, 0x03,
+    ];
+
+    let mut output: Vec<u8> = Vec::new();
+    let error = Lz77Decoder::new(&input[..]).unwrap().read_to_end(&mut output).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(error.into_inner().unwrap().downcast_ref::<Error>(),
+        Some(&Error::TruncatedInput { input_offset: 8, output_position: 3 }));
+}
+
+#[test]
+fn test_lz77_decoder_errors_2() {
+    let input: Vec<u8> = vec![
+        0x10, 0x08, 0x00, 0x00,
+        0x08,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x04,
+    ];
+
+    let mut output: Vec<u8> = Vec::new();
+    let error = Lz77Decoder::new(&input[..]).unwrap().read_to_end(&mut output).unwrap_err();
+    assert_eq!(error.into_inner().unwrap().downcast_ref::<Error>(),
+        Some(&Error::OffsetOutOfBounds { input_offset: 9, output_position: 4 }));
+}
+
+#[test]
+fn test_lz77_decoder_errors_3() {
+    assert_eq!(Lz77Decoder::new(&[0x30, 0x08, 0x00, 0x00][..]).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(Lz77Decoder::new(&[0x10, 0x08][..]).err().unwrap().kind(), io::ErrorKind::UnexpectedEof);
+
+    let options = DecompressOptions { max_output_size: Some(4), ..DecompressOptions::default() };
+    assert_eq!(Lz77Decoder::with_options(&[0x10, 0x08, 0x00, 0x00][..], &options).err().unwrap().kind(), io::ErrorKind::InvalidData);
+}
+
+// Writes `input` in pieces of varying length
+fn write_in_pieces<W: Write>(writer: &mut W, input: &[u8], rng: &mut XorShift) {
+    let mut offset = 0;
+    while offset < input.len() {
+        let length = cmp::min(rng.below(5000) + 1, input.len() - offset);
+        writer.write_all(&input[offset..offset+length]).unwrap();
+        offset += length;
+    }
+}
+
+#[test]
+fn test_lz77_encoder_1() {
+    let mut rng = XorShift::new(0x1357_9BDF);
+    let block = rng.bytes(3000, 16);
+    let input: Vec<u8> = (0..100).flat_map(|i| if i % 3 == 0 { rng.bytes(2000, 4) } else { block.clone() }).collect();
+
+    let levels = [Lz77Level::Greedy, Lz77Level::Lazy];
+    let tie_breaks = [Lz77TieBreak::Nearest, Lz77TieBreak::Farthest];
+
+    for &level in &levels {
+        for &tie_break in &tie_breaks {
+            let options = Lz77Options { level, tie_break, vram_safe: true, ..Lz77Options::default() };
+
+            let mut encoder = Lz77Encoder::with_options(Vec::new(), input.len(), &options).unwrap();
+            write_in_pieces(&mut encoder, &input, &mut rng);
+            let output = encoder.finish().unwrap();
+
+            assert_eq!(output, compress_lz77_with_options(&input, &options).unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_lz77_encoder_2() {
+    let input: Vec<u8> = (0..64).map(|i| i % 7).collect();
+
+    let mut encoder = Lz77Encoder::new(Vec::new(), input.len()).unwrap();
+    encoder.write_all(&input).unwrap();
+    let immediate = encoder.finish().unwrap();
+
+    let mut output: Vec<u8> = Vec::new();
+    Lz77Decoder::new(&immediate[..]).unwrap().read_to_end(&mut output).unwrap();
+    assert_eq!(output, input);
+}
+
+#[test]
+fn test_lz77_encoder_3() {
+    let encoder = Lz77Encoder::new(Vec::new(), 0).unwrap();
+    assert_eq!(encoder.finish().unwrap(), compress_lz77(&[], false).unwrap());
+
+    let options = Lz77Options { compress_options: CompressOptions { extended_header: true, ..CompressOptions::default() }, ..Lz77Options::default() };
+    let encoder = Lz77Encoder::with_options(Vec::new(), 0, &options).unwrap();
+    assert_eq!(encoder.finish().unwrap(), compress_lz77_with_options(&[], &options).unwrap());
+}
+
+#[test]
+fn test_lz77_encoder_errors_1() {
+    let mut encoder = Lz77Encoder::new(Vec::new(), 4).unwrap();
+    encoder.write_all(&[0x01, 0x02, 0x03]).unwrap();
+
+    let error = encoder.write_all(&[0x04, 0x05]).unwrap_err();
+    assert_eq!(error.into_inner().unwrap().downcast_ref::<Error>(), Some(&Error::SizeLimit { size: 5, limit: 4 }));
+
+    let error = encoder.finish().unwrap_err();
+    assert_eq!(error.into_inner().unwrap().downcast_ref::<Error>(), Some(&Error::SizeMismatch { expected: 4, actual: 3 }));
+}
+
+#[test]
+fn test_lz77_encoder_errors_2() {
+    let options = Lz77Options { level: Lz77Level::Optimal, ..Lz77Options::default() };
+    assert_eq!(Lz77Encoder::with_options(Vec::new(), 4, &options).err().unwrap().kind(), io::ErrorKind::InvalidInput);
+
+    let options = Lz77Options { window_size: 0, ..Lz77Options::default() };
+    assert_eq!(Lz77Encoder::with_options(Vec::new(), 4, &options).err().unwrap().kind(), io::ErrorKind::InvalidInput);
+
+    assert_eq!(Lz77Encoder::new(Vec::new(), 0x1000000).err().unwrap().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_rle_encoder_1() {
+    let mut rng = XorShift::new(0x0F1E_2D3C);
+    let input: Vec<u8> = (0..200).flat_map(|i| rng.bytes(1000, if i % 2 == 0 { 2 } else { 256 })).collect();
+
+    let mut encoder = RleEncoder::new(Vec::new(), input.len()).unwrap();
+    write_in_pieces(&mut encoder, &input, &mut rng);
+    let immediate = encoder.finish().unwrap();
+
+    assert_eq!(immediate, compress_rle(&input).unwrap());
+    assert_eq!(decompress_rle(&immediate).unwrap(), input);
+}
+
+#[test]
+fn test_rle_encoder_2() {
+    let options = CompressOptions { extended_header: true, ..CompressOptions::default() };
+    let encoder = RleEncoder::with_options(Vec::new(), 0, &options).unwrap();
+    assert_eq!(encoder.finish().unwrap(), compress_rle_with_options(&[], &options).unwrap());
+}
+
+#[test]
+fn test_rle_encoder_errors_1() {
+    let mut encoder = RleEncoder::new(Vec::new(), 2).unwrap();
+
+    let error = encoder.write_all(&[0x01, 0x02, 0x03]).unwrap_err();
+    assert_eq!(error.into_inner().unwrap().downcast_ref::<Error>(), Some(&Error::SizeLimit { size: 3, limit: 2 }));
+
+    let error = encoder.finish().unwrap_err();
+    assert_eq!(error.into_inner().unwrap().downcast_ref::<Error>(), Some(&Error::SizeMismatch { expected: 2, actual: 0 }));
+}
+
+#[test]
+fn test_encoder_padding_1() {
+    let input: Vec<u8> = (0..1000).map(|i| (i / 7) as u8).collect();
+
+    let options = Lz77Options { compress_options: CompressOptions { alignment_padding: true, ..CompressOptions::default() }, ..Lz77Options::default() };
+    let mut encoder = Lz77Encoder::with_options(Vec::new(), input.len(), &options).unwrap();
+    encoder.write_all(&input).unwrap();
+    assert_eq!(encoder.finish().unwrap(), compress_lz77_with_options(&input, &options).unwrap());
+
+    let options = CompressOptions { alignment_padding: true, ..CompressOptions::default() };
+    let mut encoder = RleEncoder::with_options(Vec::new(), input.len(), &options).unwrap();
+    encoder.write_all(&input).unwrap();
+    assert_eq!(encoder.finish().unwrap(), compress_rle_with_options(&input, &options).unwrap());
+}
+
+#[test]
+fn test_lz77_decoder_padding_1() {
+    let input: Vec<u8> = vec![
+        0x10, 0x08, 0x00, 0x00,
+        0x08,
+        0x01, 0x02, 0x03, 0x04,
+        0x10, 0x03,
+        0x00,
+        0xAA,
+    ];
+    let options = DecompressOptions { alignment_padding: true, ..DecompressOptions::default() };
+
+    let mut decoder = Lz77Decoder::with_options(&input[..], &options).unwrap();
+    let mut output: Vec<u8> = Vec::new();
+    decoder.read_to_end(&mut output).unwrap();
+    assert_eq!(output.len(), 8);
+    assert_eq!(decoder.input_position(), 12);
+    assert_eq!(decoder.into_inner(), &[0xAA]);
+
+    let error = Lz77Decoder::with_options(&input[..11], &options).unwrap().read_to_end(&mut output).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+    let mut input = input;
+    input[11] = 0xFF;
+    let error = Lz77Decoder::with_options(&input[..], &options).unwrap().read_to_end(&mut output).unwrap_err();
+    assert_eq!(error.into_inner().unwrap().downcast_ref::<Error>(), Some(&Error::InvalidPadding { input_offset: 11 }));
+}
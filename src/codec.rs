@@ -0,0 +1,147 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use bios::BiosMethod;
+use error::Result;
+use game_specific::wario_land_4::{Wl4Rle8Codec, Wl4Rle16Codec, Wl4RleCodec};
+
+pub trait Codec {
+    fn name(&self) -> &str;
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>>;
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>>;
+    fn detect(&self, input: &[u8]) -> bool;
+}
+
+pub struct CodecRegistry {
+    codecs: Vec<Box<dyn Codec>>,
+}
+
+impl CodecRegistry {
+    // The registry starts out with every built-in codec
+    pub fn new() -> CodecRegistry {
+        let mut registry = CodecRegistry::empty();
+
+        for &method in &[BiosMethod::Lz77, BiosMethod::Huffman4, BiosMethod::Huffman8, BiosMethod::Rle,
+            BiosMethod::DiffFilter8, BiosMethod::DiffFilter16] {
+            registry.register(Box::new(method));
+        }
+
+        registry.register(Box::new(Wl4Rle8Codec));
+        registry.register(Box::new(Wl4Rle16Codec));
+        registry.register(Box::new(Wl4RleCodec));
+        registry
+    }
+
+    pub fn empty() -> CodecRegistry {
+        CodecRegistry { codecs: Vec::new() }
+    }
+
+    // Registering a codec under an existing name replaces the earlier one
+    pub fn register(&mut self, codec: Box<dyn Codec>) {
+        self.codecs.retain(|existing| existing.name() != codec.name());
+        self.codecs.push(codec);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Codec> {
+        self.codecs.iter()
+            .find(|codec| codec.name() == name)
+            .map(|codec| &**codec)
+    }
+
+    // Codecs are tried in registration order, so the more specific formats
+    // have to be registered before the ones accepting a wider range of input.
+    pub fn detect(&self, input: &[u8]) -> Option<&dyn Codec> {
+        self.codecs.iter()
+            .find(|codec| codec.detect(input))
+            .map(|codec| &**codec)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.codecs.iter().map(|codec| codec.name()).collect()
+    }
+}
+
+impl Default for CodecRegistry {
+    fn default() -> CodecRegistry {
+        CodecRegistry::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codec::{Codec, CodecRegistry};
+    use error::{Error, Result};
+
+    struct ReverseCodec;
+
+    impl Codec for ReverseCodec {
+        fn name(&self) -> &str {
+            "reverse"
+        }
+
+        fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.iter().rev().cloned().collect())
+        }
+
+        fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            self.compress(input)
+        }
+
+        fn detect(&self, _input: &[u8]) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_builtin_codecs() {
+        let registry = CodecRegistry::default();
+        let input: Vec<u8> = (0..256).map(|i| (i / 16) as u8).collect();
+
+        for name in registry.names() {
+            let codec = registry.get(name).unwrap();
+            let immediate = codec.compress(&input).unwrap();
+
+            assert!(codec.detect(&immediate));
+            assert_eq!(codec.decompress(&immediate).unwrap(), input);
+            assert_eq!(registry.detect(&immediate).unwrap().decompress(&immediate).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let registry = CodecRegistry::default();
+
+        assert_eq!(registry.detect(&[0x10, 0x00, 0x00, 0x00]).map(|codec| codec.name()), Some("lz77"));
+        assert_eq!(registry.detect(&[0x24, 0x00, 0x00, 0x00]).map(|codec| codec.name()), Some("huffman4"));
+        assert_eq!(registry.detect(&[0x82, 0x00, 0x00, 0x00]).map(|codec| codec.name()), Some("diff16"));
+        assert_eq!(registry.detect(&[0x02, 0x00, 0x00]).map(|codec| codec.name()), Some("wl4-rle16"));
+        assert!(registry.detect(&[0x83, 0x00, 0x00, 0x00]).is_none());
+        assert!(registry.detect(&[]).is_none());
+    }
+
+    #[test]
+    fn test_decompress_mismatch() {
+        let registry = CodecRegistry::default();
+        let input: Vec<u8> = vec![0x28, 0x00, 0x00, 0x00];
+
+        assert_eq!(registry.get("huffman4").unwrap().decompress(&input), Err(Error::HeaderMismatch { input_offset: 0 }));
+        assert_eq!(registry.get("wl4-rle8").unwrap().decompress(&input), Err(Error::HeaderMismatch { input_offset: 0 }));
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(CodecRegistry::new().names(), CodecRegistry::default().names());
+        assert!(CodecRegistry::new().get("lz77").is_some());
+        assert!(CodecRegistry::empty().names().is_empty());
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = CodecRegistry::empty();
+        assert!(registry.get("reverse").is_none());
+
+        registry.register(Box::new(ReverseCodec));
+        registry.register(Box::new(ReverseCodec));
+        assert_eq!(registry.names(), vec!["reverse"]);
+        assert_eq!(registry.get("reverse").unwrap().compress(&[1, 2, 3]).unwrap(), vec![3, 2, 1]);
+    }
+}
@@ -0,0 +1,110 @@
+use core::fmt;
+use core::result;
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::io;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Error {
+    HeaderMismatch {
+        input_offset: usize,
+    },
+    TruncatedInput {
+        input_offset: usize,
+        output_position: usize,
+    },
+    OffsetOutOfBounds {
+        input_offset: usize,
+        output_position: usize,
+    },
+    LengthOverrun {
+        input_offset: usize,
+        output_position: usize,
+    },
+    InvalidTree {
+        input_offset: usize,
+        output_position: usize,
+    },
+    SizeLimit {
+        size: usize,
+        limit: usize,
+    },
+    SizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    InvalidPadding {
+        input_offset: usize,
+    },
+    InvalidSize {
+        size: usize,
+    },
+    InvalidArgument(&'static str),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::HeaderMismatch { input_offset } =>
+                write!(f, "header mismatch at input offset {}", input_offset),
+            Error::TruncatedInput { input_offset, output_position } =>
+                write!(f, "truncated input at input offset {} (output position {})", input_offset, output_position),
+            Error::OffsetOutOfBounds { input_offset, output_position } =>
+                write!(f, "offset out of bounds at input offset {} (output position {})", input_offset, output_position),
+            Error::LengthOverrun { input_offset, output_position } =>
+                write!(f, "length out of bounds at input offset {} (output position {})", input_offset, output_position),
+            Error::InvalidTree { input_offset, output_position } =>
+                write!(f, "invalid tree node at input offset {} (output position {})", input_offset, output_position),
+            Error::SizeLimit { size, limit } =>
+                write!(f, "size {} exceeds the limit of {}", size, limit),
+            Error::SizeMismatch { expected, actual } =>
+                write!(f, "size {} does not match the expected size of {}", actual, expected),
+            Error::InvalidPadding { input_offset } =>
+                write!(f, "non-zero padding at input offset {}", input_offset),
+            Error::InvalidSize { size } =>
+                write!(f, "size {} is not valid for the stream", size),
+            Error::InvalidArgument(reason) =>
+                write!(f, "invalid argument: {}", reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    fn from(error: Error) -> io::Error {
+        let kind = match error {
+            Error::TruncatedInput { .. } => io::ErrorKind::UnexpectedEof,
+            Error::InvalidArgument(_) => io::ErrorKind::InvalidInput,
+            _ => io::ErrorKind::InvalidData,
+        };
+
+        io::Error::new(kind, error)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::io;
+    use error::Error;
+
+    #[test]
+    fn test_into_io_error() {
+        let error: io::Error = Error::TruncatedInput { input_offset: 4, output_position: 0 }.into();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let error: io::Error = Error::HeaderMismatch { input_offset: 0 }.into();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error: io::Error = Error::InvalidSize { size: 3 }.into();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error: io::Error = Error::InvalidArgument("window size out of bounds").into();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
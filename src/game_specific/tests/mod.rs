@@ -1 +1,2 @@
+mod no_panic;
 mod wario_land_4;
@@ -0,0 +1,49 @@
+use game_specific::wario_land_4::{compress_wl4_rle, decompress_wl4_rle, decompress_wl4_rle_with_length};
+use game_specific::wario_land_4::{decompress_wl4_rle8, decompress_wl4_rle16};
+use utils::XorShift;
+
+fn decompress_all(input: &[u8]) {
+    let _ = decompress_wl4_rle(input);
+    let _ = decompress_wl4_rle_with_length(input);
+    let _ = decompress_wl4_rle8(input);
+    let _ = decompress_wl4_rle16(input);
+}
+
+#[test]
+fn test_random_input() {
+    let mut rng = XorShift::new(0x1234_5678);
+
+    for _ in 0..2000 {
+        let length = rng.below(64);
+        let mut input = rng.bytes(length, 256);
+
+        if !input.is_empty() {
+            input[0] = [0x01, 0x02][rng.below(2)];
+        }
+
+        decompress_all(&input);
+    }
+}
+
+#[test]
+fn test_mutated_streams() {
+    let mut rng = XorShift::new(0x9ABC_DEF0);
+
+    for _ in 0..200 {
+        let length = rng.below(128);
+        let alphabet = 1 + rng.below(255);
+        let input = rng.bytes(length, alphabet);
+        let stream = compress_wl4_rle(&input).unwrap();
+
+        for length in 0..stream.len() {
+            decompress_all(&stream[..length]);
+        }
+
+        let mut mutated = stream.clone();
+        for _ in 0..4 {
+            let index = rng.below(mutated.len());
+            mutated[index] ^= 1 << rng.below(8);
+            decompress_all(&mutated);
+        }
+    }
+}
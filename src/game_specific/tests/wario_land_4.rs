@@ -1,6 +1,9 @@
-use game_specific::wario_land_4::{compress_wl4_rle8, decompress_wl4_rle8};
-use game_specific::wario_land_4::{compress_wl4_rle16, decompress_wl4_rle16};
+use game_specific::wario_land_4::{decompress_wl4_rle8, decompress_wl4_rle16};
 use game_specific::wario_land_4::{compress_wl4_rle, decompress_wl4_rle};
+use game_specific::wario_land_4::{decompress_wl4_rle8_with_length, decompress_wl4_rle16_with_length};
+use game_specific::wario_land_4::{decompress_wl4_rle_with_length, decompress_wl4_rle_with_options};
+use error::Error;
+use options::DecompressOptions;
 
 #[test]
 fn test_decompress_1() {
@@ -87,3 +90,90 @@ fn test_compress_and_decompress_3() {
     let output = decompress_wl4_rle(&immediate).unwrap();
     assert_eq!(input, output);
 }
+
+#[test]
+fn test_decompress_with_length_1() {
+    let input: Vec<u8> = vec![
+        0x01,
+        0x04, 0x01, 0x02, 0x03, 0x04,
+        0x84, 0x05,
+        0x00,
+        0xAA, 0xBB,
+    ];
+
+    let (output, length) = decompress_wl4_rle8_with_length(&input).unwrap();
+    assert_eq!(output.len(), 8);
+    assert_eq!(length, 9);
+
+    let (output, length) = decompress_wl4_rle_with_length(&input).unwrap();
+    assert_eq!(output.len(), 8);
+    assert_eq!(length, 9);
+}
+
+#[test]
+fn test_decompress_with_length_2() {
+    let input: Vec<u8> = vec![
+        0x02,
+        0x00, 0x04, 0x01, 0x02, 0x03, 0x04,
+        0x80, 0x04, 0x05,
+        0x00, 0x00,
+        0xAA, 0xBB,
+    ];
+
+    let (output, length) = decompress_wl4_rle16_with_length(&input).unwrap();
+    assert_eq!(output.len(), 8);
+    assert_eq!(length, 12);
+}
+
+#[test]
+fn test_decompress_errors_1() {
+    let input: Vec<u8> = vec![
+        0x01,
+        0x04, 0x01, 0x02,
+    ];
+
+    assert_eq!(decompress_wl4_rle8(&input), Err(Error::TruncatedInput { input_offset: 4, output_position: 2 }));
+    assert_eq!(decompress_wl4_rle(&input), Err(Error::TruncatedInput { input_offset: 4, output_position: 2 }));
+}
+
+#[test]
+fn test_decompress_errors_2() {
+    let input: Vec<u8> = vec![
+        0x03,
+        0x00,
+    ];
+
+    assert_eq!(decompress_wl4_rle(&input), Err(Error::HeaderMismatch { input_offset: 0 }));
+    assert_eq!(decompress_wl4_rle16(&input), Err(Error::HeaderMismatch { input_offset: 0 }));
+}
+
+#[test]
+fn test_decompress_with_options_1() {
+    let input: Vec<u8> = vec![
+        0x02,
+        0xFF, 0xFF, 0x00,
+        0xFF, 0xFF, 0x00,
+        0x00, 0x00,
+    ];
+
+    let options = DecompressOptions { max_output_size: Some(0x8000), ..DecompressOptions::default() };
+    assert_eq!(decompress_wl4_rle_with_options(&input, &options), Err(Error::SizeLimit { size: 0xFFFE, limit: 0x8000 }));
+}
+
+#[test]
+fn test_decompress_with_options_2() {
+    let input: Vec<u8> = vec![
+        0x01,
+        0x84, 0x55,
+        0x00,
+    ];
+
+    let options = DecompressOptions { expected_size: Some(4), ..DecompressOptions::default() };
+    assert_eq!(decompress_wl4_rle_with_options(&input, &options).unwrap(), (vec![0x55; 4], 4));
+
+    let options = DecompressOptions { expected_size: Some(3), ..DecompressOptions::default() };
+    assert_eq!(decompress_wl4_rle_with_options(&input, &options), Err(Error::SizeLimit { size: 4, limit: 3 }));
+
+    let options = DecompressOptions { expected_size: Some(5), ..DecompressOptions::default() };
+    assert_eq!(decompress_wl4_rle_with_options(&input, &options), Err(Error::SizeMismatch { expected: 5, actual: 4 }));
+}
@@ -1,128 +1,174 @@
-use std::io::{Write, Result, Error, ErrorKind, Cursor};
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use num::FromPrimitive;
-use utils::{consecutive_count, non_consecutive_count};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use codec::Codec;
+use error::{Error, Result};
+use options::{check_output_size, output_limit, DecompressOptions};
+use utils::{ByteReader, RunLengths, WriteExt};
 
-enum_from_primitive! {
-    #[derive(Debug, Eq, PartialEq)]
-    enum RleType {
-        Rle8  = 1,
-        Rle16 = 2,
+#[derive(Debug, Eq, PartialEq)]
+enum RleType {
+    Rle8  = 1,
+    Rle16 = 2,
+}
+
+impl TryFrom<u8> for RleType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<RleType> {
+        match value {
+            1 => Ok(RleType::Rle8),
+            2 => Ok(RleType::Rle16),
+            _ => Err(Error::HeaderMismatch { input_offset: 0 }),
+        }
     }
 }
 
 pub fn compress_wl4_rle8(input: &[u8]) -> Result<Vec<u8>> {
-    let mut output = Vec::new();
-    output.write_u8(RleType::Rle8 as u8)?;
+    let mut output: Vec<u8> = Vec::new();
+    output.write_u8(RleType::Rle8 as u8);
+
+    let run_lengths = RunLengths::new(input);
 
     let mut offset = 0;
     while offset < input.len() {
-        let length = consecutive_count(&input[offset..], 0x7F);
+        let length = run_lengths.consecutive_count(offset, 0x7F);
         if length == 1 {
-            let length = non_consecutive_count(&input[offset..], 0x7F, 2);
-            output.write_u8(length as u8)?;
-            output.write_all(&input[offset..offset+length])?;
+            let length = run_lengths.non_consecutive_count(offset, 0x7F, 2);
+            output.write_u8(length as u8);
+            output.extend_from_slice(&input[offset..offset+length]);
             offset += length;
         } else {
-            output.write_u8(0x80 | length as u8)?;
-            output.write_u8(input[offset])?;
+            output.write_u8(0x80 | length as u8);
+            output.write_u8(input[offset]);
             offset += length;
         }
     }
 
-    output.write_u8(0)?;
+    output.write_u8(0);
     Ok(output)
 }
 
-pub fn decompress_wl4_rle8(input: &[u8]) -> Result<Vec<u8>> {
-    let mut cursor = Cursor::new(input);
-    let mut output = Vec::new();
+pub fn decompress_wl4_rle8_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut reader = ByteReader::new(input);
+    let mut output: Vec<u8> = Vec::new();
+    let limit = output_limit(options);
 
-    let rle_type = RleType::from_u8(cursor.read_u8()?);
+    let rle_type = RleType::try_from(reader.read_u8(0)?).ok();
     if rle_type == Some(RleType::Rle8) {
         loop {
-            let block = cursor.read_u8()?;
+            let block = reader.read_u8(output.len())?;
             if block == 0 {
                 // End of data
                 break;
-            } else if block & 0x80 == 0 {
+            }
+
+            let length = (block & 0x7F) as usize;
+            if output.len() + length > limit {
+                return Err(Error::SizeLimit { size: output.len() + length, limit });
+            }
+
+            if block & 0x80 == 0 {
                 // Uncompressed
-                let length = block & 0x7F;
                 for _ in 0..length {
-                    output.write_u8(cursor.read_u8()?)?;
+                    output.write_u8(reader.read_u8(output.len())?);
                 }
             } else {
                 // Run-length encoded
-                let length = block & 0x7F;
-                let data = cursor.read_u8()?;
+                let data = reader.read_u8(output.len())?;
                 for _ in 0..length {
-                    output.write_u8(data)?;
+                    output.write_u8(data);
                 }
             }
         }
 
-        Ok(output)
+        check_output_size(options, output.len())?;
+        Ok((output, reader.position()))
     } else {
-        Err(Error::new(ErrorKind::InvalidData, "compression header mismatch"))
+        Err(Error::HeaderMismatch { input_offset: 0 })
     }
 }
 
+pub fn decompress_wl4_rle8_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    decompress_wl4_rle8_with_options(input, &DecompressOptions::default())
+}
+
+pub fn decompress_wl4_rle8(input: &[u8]) -> Result<Vec<u8>> {
+    decompress_wl4_rle8_with_length(input).map(|(output, _)| output)
+}
+
 pub fn compress_wl4_rle16(input: &[u8]) -> Result<Vec<u8>> {
-    let mut output = Vec::new();
-    output.write_u8(RleType::Rle16 as u8)?;
+    let mut output: Vec<u8> = Vec::new();
+    output.write_u8(RleType::Rle16 as u8);
+
+    let run_lengths = RunLengths::new(input);
 
     let mut offset = 0;
     while offset < input.len() {
-        let length = consecutive_count(&input[offset..], 0x7FFF);
+        let length = run_lengths.consecutive_count(offset, 0x7FFF);
         if length == 1 {
-            let length = non_consecutive_count(&input[offset..], 0x7FFF, 2);
-            output.write_u16::<BigEndian>(length as u16)?;
-            output.write_all(&input[offset..offset+length])?;
+            let length = run_lengths.non_consecutive_count(offset, 0x7FFF, 2);
+            output.write_u16_be(length as u16);
+            output.extend_from_slice(&input[offset..offset+length]);
             offset += length;
         } else {
-            output.write_u16::<BigEndian>(0x8000 | length as u16)?;
-            output.write_u8(input[offset])?;
+            output.write_u16_be(0x8000 | length as u16);
+            output.write_u8(input[offset]);
             offset += length;
         }
     }
 
-    output.write_u16::<BigEndian>(0)?;
+    output.write_u16_be(0);
     Ok(output)
 }
 
-pub fn decompress_wl4_rle16(input: &[u8]) -> Result<Vec<u8>> {
-    let mut cursor = Cursor::new(input);
-    let mut output = Vec::new();
+pub fn decompress_wl4_rle16_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut reader = ByteReader::new(input);
+    let mut output: Vec<u8> = Vec::new();
+    let limit = output_limit(options);
 
-    let rle_type = RleType::from_u8(cursor.read_u8()?);
+    let rle_type = RleType::try_from(reader.read_u8(0)?).ok();
     if rle_type == Some(RleType::Rle16) {
         loop {
-            let block = cursor.read_u16::<BigEndian>()?;
+            let block = reader.read_u16_be(output.len())?;
             if block == 0 {
                 // End of data
                 break;
-            } else if block & 0x8000 == 0 {
+            }
+
+            let length = (block & 0x7FFF) as usize;
+            if output.len() + length > limit {
+                return Err(Error::SizeLimit { size: output.len() + length, limit });
+            }
+
+            if block & 0x8000 == 0 {
                 // Uncompressed
-                let length = block & 0x7FFF;
                 for _ in 0..length {
-                    output.write_u8(cursor.read_u8()?)?;
+                    output.write_u8(reader.read_u8(output.len())?);
                 }
             } else {
                 // Run-length encoded
-                let length = block & 0x7FFF;
-                let data = cursor.read_u8()?;
+                let data = reader.read_u8(output.len())?;
                 for _ in 0..length {
-                    output.write_u8(data)?;
+                    output.write_u8(data);
                 }
             }
         }
 
-        Ok(output)
+        check_output_size(options, output.len())?;
+        Ok((output, reader.position()))
     } else {
-        Err(Error::new(ErrorKind::InvalidData, "compression header mismatch"))
+        Err(Error::HeaderMismatch { input_offset: 0 })
     }
 }
 
+pub fn decompress_wl4_rle16_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    decompress_wl4_rle16_with_options(input, &DecompressOptions::default())
+}
+
+pub fn decompress_wl4_rle16(input: &[u8]) -> Result<Vec<u8>> {
+    decompress_wl4_rle16_with_length(input).map(|(output, _)| output)
+}
+
 pub fn compress_wl4_rle(input: &[u8]) -> Result<Vec<u8>> {
     let output_rle8 = compress_wl4_rle8(input)?;
     let output_rle16 = compress_wl4_rle16(input)?;
@@ -134,13 +180,80 @@ pub fn compress_wl4_rle(input: &[u8]) -> Result<Vec<u8>> {
     }
 }
 
-pub fn decompress_wl4_rle(input: &[u8]) -> Result<Vec<u8>> {
-    let mut cursor = Cursor::new(input);
-    let rle_type = RleType::from_u8(cursor.read_u8()?);
+pub fn decompress_wl4_rle_with_options(input: &[u8], options: &DecompressOptions) -> Result<(Vec<u8>, usize)> {
+    let mut reader = ByteReader::new(input);
+    let rle_type = RleType::try_from(reader.read_u8(0)?)?;
 
     match rle_type {
-        Some(RleType::Rle8) => decompress_wl4_rle8(input),
-        Some(RleType::Rle16) => decompress_wl4_rle16(input),
-        None => Err(Error::new(ErrorKind::InvalidData, "unknown compression header")),
+        RleType::Rle8 => decompress_wl4_rle8_with_options(input, options),
+        RleType::Rle16 => decompress_wl4_rle16_with_options(input, options),
+    }
+}
+
+pub fn decompress_wl4_rle_with_length(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    decompress_wl4_rle_with_options(input, &DecompressOptions::default())
+}
+
+pub fn decompress_wl4_rle(input: &[u8]) -> Result<Vec<u8>> {
+    decompress_wl4_rle_with_length(input).map(|(output, _)| output)
+}
+
+pub struct Wl4Rle8Codec;
+
+impl Codec for Wl4Rle8Codec {
+    fn name(&self) -> &str {
+        "wl4-rle8"
+    }
+
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        compress_wl4_rle8(input)
+    }
+
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        decompress_wl4_rle8(input)
+    }
+
+    fn detect(&self, input: &[u8]) -> bool {
+        input.first() == Some(&(RleType::Rle8 as u8))
+    }
+}
+
+pub struct Wl4Rle16Codec;
+
+impl Codec for Wl4Rle16Codec {
+    fn name(&self) -> &str {
+        "wl4-rle16"
+    }
+
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        compress_wl4_rle16(input)
+    }
+
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        decompress_wl4_rle16(input)
+    }
+
+    fn detect(&self, input: &[u8]) -> bool {
+        input.first() == Some(&(RleType::Rle16 as u8))
+    }
+}
+
+pub struct Wl4RleCodec;
+
+impl Codec for Wl4RleCodec {
+    fn name(&self) -> &str {
+        "wl4-rle"
+    }
+
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        compress_wl4_rle(input)
+    }
+
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        decompress_wl4_rle(input)
+    }
+
+    fn detect(&self, input: &[u8]) -> bool {
+        input.first().is_some_and(|&value| RleType::try_from(value).is_ok())
     }
 }
@@ -1,11 +1,18 @@
-#![feature(inclusive_range_syntax)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-extern crate byteorder;
 #[macro_use]
-extern crate enum_primitive;
-extern crate num;
+extern crate alloc;
+extern crate byteorder;
+#[cfg(any(feature = "std", test))]
+extern crate core;
 
 pub mod bios;
+pub mod codec;
 pub mod game_specific;
 
+mod error;
+mod options;
 mod utils;
+
+pub use error::{Error, Result};
+pub use options::{CompressOptions, DecompressOptions};
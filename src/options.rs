@@ -0,0 +1,40 @@
+use core::cmp;
+use error::{Error, Result};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct CompressOptions {
+    pub extended_header: bool,
+    pub alignment_padding: bool,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct DecompressOptions {
+    pub max_output_size: Option<usize>,
+    pub expected_size: Option<usize>,
+    pub extended_header: bool,
+    pub alignment_padding: bool,
+}
+
+pub fn check_output_size(options: &DecompressOptions, size: usize) -> Result<()> {
+    if let Some(limit) = options.max_output_size {
+        if size > limit {
+            return Err(Error::SizeLimit { size, limit });
+        }
+    }
+
+    if let Some(expected) = options.expected_size {
+        if size != expected {
+            return Err(Error::SizeMismatch { expected, actual: size });
+        }
+    }
+
+    Ok(())
+}
+
+// Formats without a size field can only be stopped once the output grows
+// past what the caller is willing to accept.
+pub fn output_limit(options: &DecompressOptions) -> usize {
+    cmp::min(options.max_output_size.unwrap_or(usize::MAX), options.expected_size.unwrap_or(usize::MAX))
+}
@@ -1,3 +1,10 @@
+use core::cmp;
+use alloc::vec::Vec;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use error::{Error, Result};
+
+// Reference implementations of the queries answered by `RunLengths`
+#[cfg(test)]
 pub fn consecutive_count<T: Eq>(buf: &[T], max_length: usize) -> usize {
     let mut i = 0;
     while (i < buf.len()) && (i < max_length) && (buf[0] == buf[i]) {
@@ -6,6 +13,7 @@ pub fn consecutive_count<T: Eq>(buf: &[T], max_length: usize) -> usize {
     i
 }
 
+#[cfg(test)]
 pub fn non_consecutive_count<T: Eq>(buf: &[T], max_length: usize, consecutive_threshold: usize) -> usize {
     let mut i = 0;
     while (i < buf.len()) && (i < max_length) && (consecutive_count(&buf[i..], max_length) < consecutive_threshold) {
@@ -22,9 +30,157 @@ pub fn same_count<T: Eq>(buf1: &[T], buf2: &[T], max_length: usize) -> usize {
     i
 }
 
+// Lengths of the runs of equal values starting at every position, computed
+// once up front so that scanning a whole buffer stays linear.
+pub struct RunLengths {
+    lengths: Vec<usize>,
+}
+
+impl RunLengths {
+    pub fn new<T: Eq>(buf: &[T]) -> RunLengths {
+        let mut lengths: Vec<usize> = vec![1; buf.len()];
+        for i in (0..buf.len().saturating_sub(1)).rev() {
+            if buf[i] == buf[i + 1] {
+                lengths[i] = lengths[i + 1] + 1;
+            }
+        }
+        RunLengths { lengths }
+    }
+
+    // Same as `consecutive_count(&buf[offset..], max_length)`
+    pub fn consecutive_count(&self, offset: usize, max_length: usize) -> usize {
+        self.lengths.get(offset).map_or(0, |&length| cmp::min(length, max_length))
+    }
+
+    // Same as `non_consecutive_count(&buf[offset..], max_length, consecutive_threshold)`.
+    // The scan only covers the returned span, which the encoders then consume.
+    pub fn non_consecutive_count(&self, offset: usize, max_length: usize, consecutive_threshold: usize) -> usize {
+        let mut i = 0;
+        while (offset + i < self.lengths.len()) && (i < max_length) && (self.consecutive_count(offset + i, max_length) < consecutive_threshold) {
+            i += 1;
+        }
+        i
+    }
+}
+
+pub struct ByteReader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(input: &'a [u8]) -> ByteReader<'a> {
+        ByteReader {
+            input,
+            position: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    // The output position is only used for error reporting
+    pub fn read_bytes(&mut self, length: usize, output_position: usize) -> Result<&'a [u8]> {
+        let data = self.position.checked_add(length)
+            .and_then(|end| self.input.get(self.position..end))
+            .ok_or(Error::TruncatedInput { input_offset: self.position, output_position })?;
+
+        self.position += length;
+        Ok(data)
+    }
+
+    pub fn read_u8(&mut self, output_position: usize) -> Result<u8> {
+        Ok(self.read_bytes(1, output_position)?[0])
+    }
+
+    pub fn read_u16_le(&mut self, output_position: usize) -> Result<u16> {
+        Ok(LittleEndian::read_u16(self.read_bytes(2, output_position)?))
+    }
+
+    pub fn read_u16_be(&mut self, output_position: usize) -> Result<u16> {
+        Ok(BigEndian::read_u16(self.read_bytes(2, output_position)?))
+    }
+
+    pub fn read_u24_le(&mut self, output_position: usize) -> Result<u32> {
+        Ok(LittleEndian::read_u24(self.read_bytes(3, output_position)?))
+    }
+
+    pub fn read_u32_le(&mut self, output_position: usize) -> Result<u32> {
+        Ok(LittleEndian::read_u32(self.read_bytes(4, output_position)?))
+    }
+}
+
+pub trait WriteExt {
+    fn write_u8(&mut self, value: u8);
+    fn write_u16_le(&mut self, value: u16);
+    fn write_u16_be(&mut self, value: u16);
+    fn write_u24_le(&mut self, value: u32);
+    fn write_u32_le(&mut self, value: u32);
+}
+
+impl WriteExt for Vec<u8> {
+    fn write_u8(&mut self, value: u8) {
+        self.push(value);
+    }
+
+    fn write_u16_le(&mut self, value: u16) {
+        let mut buffer = [0; 2];
+        LittleEndian::write_u16(&mut buffer, value);
+        self.extend_from_slice(&buffer);
+    }
+
+    fn write_u16_be(&mut self, value: u16) {
+        let mut buffer = [0; 2];
+        BigEndian::write_u16(&mut buffer, value);
+        self.extend_from_slice(&buffer);
+    }
+
+    fn write_u24_le(&mut self, value: u32) {
+        let mut buffer = [0; 3];
+        LittleEndian::write_u24(&mut buffer, value);
+        self.extend_from_slice(&buffer);
+    }
+
+    fn write_u32_le(&mut self, value: u32) {
+        let mut buffer = [0; 4];
+        LittleEndian::write_u32(&mut buffer, value);
+        self.extend_from_slice(&buffer);
+    }
+}
+
+// Deterministic generator for the randomized tests, avoiding a dependency
+#[cfg(test)]
+pub struct XorShift {
+    state: u32,
+}
+
 #[cfg(test)]
+impl XorShift {
+    pub fn new(seed: u32) -> XorShift {
+        XorShift { state: seed | 1 }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.next_u32() as usize % bound
+    }
+
+    pub fn bytes(&mut self, length: usize, alphabet: usize) -> Vec<u8> {
+        (0..length).map(|_| self.below(alphabet) as u8).collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests {
-    use utils::{consecutive_count, non_consecutive_count, same_count};
+    use utils::{consecutive_count, non_consecutive_count, same_count, RunLengths, XorShift};
 
     #[test]
     fn test_consecutive_count() {
@@ -46,6 +202,27 @@ mod tests {
         assert_eq!(non_consecutive_count::<u8>(&vec![0x00, 0x00, 0x00, 0x01, 0x01, 0x01], 9, 3), 0);
     }
 
+    #[test]
+    fn test_run_lengths() {
+        let mut rng = XorShift::new(0x0024_0024);
+
+        for alphabet in 1..5 {
+            let buf = rng.bytes(300, alphabet);
+            let run_lengths = RunLengths::new(&buf);
+
+            for offset in 0..=buf.len() {
+                for &max_length in &[1, 2, 3, 9, 0x82] {
+                    assert_eq!(run_lengths.consecutive_count(offset, max_length), consecutive_count(&buf[offset..], max_length));
+
+                    for threshold in 1..5 {
+                        assert_eq!(run_lengths.non_consecutive_count(offset, max_length, threshold),
+                            non_consecutive_count(&buf[offset..], max_length, threshold));
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_same_count() {
         assert_eq!(same_count::<u8>(&vec![                ], &vec![                ], 6), 0);